@@ -1,58 +1,1527 @@
-use std::{collections::VecDeque, marker::PhantomData};
+mod bounds;
+mod coordinate;
+mod dual;
+mod dynamic;
+mod implicit;
+mod metric;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod point;
+#[cfg(feature = "serde")]
+mod serialize;
+mod split;
+#[cfg(test)]
+mod tests;
+mod walk;
 
+use std::marker::PhantomData;
+
+use crate::stack_vec::StackVec;
+use split::split_range;
+
+pub use bounds::Bounds;
+pub use coordinate::Coordinate;
+pub use dual::{JoinWithin, PairsWithin};
+pub use dynamic::DynamicKDTree;
+pub use implicit::ImplicitKDTree;
+pub use metric::{Chebyshev, Manhattan, Metric, SquaredEuclidean, WeightedEuclidean};
+pub use split::Split;
+pub use walk::{NodeInfo, Walk};
+
+/// Point with `K` coordinates of type `T`.
+///
+/// Trees only ever ask for axes below `K` and check that in debug builds,
+/// `K` itself has to be at least one.
+/// ```
+/// use kuina::kd_tree::{KDTree, Point};
+/// struct Feature([f32; 8]);
+/// impl Point<f32, 8> for Feature {
+///     fn get(&self, index: usize) -> f32 {
+///         self.0[index]
+///     }
+/// }
+///
+/// let tree = KDTree::<f32, Feature, 8, 2>::from([Feature([0.0; 8]), Feature([1.0; 8])]);
+/// assert_eq!(tree.nearest(&Feature([0.75; 8])).map(|f| f.0[0]), Some(1.0));
+/// ```
 pub trait Point<T, const K: usize>
 where
-    T: Ord,
+    T: Coordinate,
+{
+    fn get(&self, index: usize) -> T;
+}
+
+/// `Send` with the `rayon` feature and implemented by every type without
+/// it, batch queries only hand their answers between threads in parallel.
+#[cfg(feature = "rayon")]
+pub trait MaybeSend: Send {}
+#[cfg(feature = "rayon")]
+impl<T: Send + ?Sized> MaybeSend for T {}
+/// `Send` with the `rayon` feature and implemented by every type without
+/// it, batch queries only hand their answers between threads in parallel.
+#[cfg(not(feature = "rayon"))]
+pub trait MaybeSend {}
+#[cfg(not(feature = "rayon"))]
+impl<T: ?Sized> MaybeSend for T {}
+
+/// `Sync` with the `rayon` feature and implemented by every type without
+/// it, batch queries only share the tree between threads in parallel.
+#[cfg(feature = "rayon")]
+pub trait MaybeSync: Sync {}
+#[cfg(feature = "rayon")]
+impl<T: Sync + ?Sized> MaybeSync for T {}
+/// `Sync` with the `rayon` feature and implemented by every type without
+/// it, batch queries only share the tree between threads in parallel.
+#[cfg(not(feature = "rayon"))]
+pub trait MaybeSync {}
+#[cfg(not(feature = "rayon"))]
+impl<T: ?Sized> MaybeSync for T {}
+
+/// Point tagged with its position in the input array.
+struct Indexed<P> {
+    index: usize,
+    point: P,
+}
+
+impl<T, P, const K: usize> Point<T, K> for Indexed<P>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    fn get(&self, index: usize) -> T {
+        self.point.get(index)
+    }
+}
+
+/// Reads the coordinate of `point` on `axis`, checking it in debug builds.
+fn at<T, P, const K: usize>(point: &P, axis: usize) -> T
+where
+    P: Point<T, K>,
+    T: Coordinate,
 {
-    type Output;
-    fn get(&self, index: usize) -> Self::Output;
+    debug_assert!(axis < K, "axis {axis} is out of range for {K} dimensions");
+    point.get(axis)
 }
 
+/// Static tree over `N` points with `K` coordinates of type `T`.
+///
+/// The points and one node per point are stored inline, building and
+/// querying the tree never allocates unless it keeps tight node boxes, see
+/// [`KDTreeBuilder::tight_bounds`].
+///
+/// Inline storage takes about `N * (size_of::<P>() + 72)` bytes on 64 bit
+/// targets, and building by value needs a few times that on the stack. A
+/// tree of 20 000 three dimensional `f32` points overflows the 8 MB main
+/// thread stack in debug builds, 50 000 in release builds. Build larger
+/// trees with [`KDTreeBuilder::build_boxed`] instead, it allocates the tree
+/// and its scratch space on the heap.
+///
+/// Building rejects points with NaN coordinates by panicking. Queries must
+/// not have NaN coordinates either, debug builds panic on them and release
+/// builds answer with an arbitrary point.
+///
+/// # Duplicates
+///
+/// Medians split points by position rather than by value, so every split
+/// leaves half of the points on each side even when many of them share a
+/// coordinate or are identical: building terminates and the tree stays
+/// balanced whatever the input. Points equal to a split point on its axis
+/// may lie on either side of it, so queries reaching the split plane walk
+/// both sides and return every duplicate.
+/// ```
+/// use kuina::kd_tree::KDTree;
+/// let same = KDTree::<u8, [u8; 2], 2, 1000>::from([[7, 7]; 1000]);
+/// assert_eq!(same.depth(), 10);
+/// assert_eq!(same.within_radius(&[7, 7], 0).count(), 1000);
+/// assert_eq!(same.range(&[7, 7], &[7, 7]).count(), 1000);
+/// let found = same.k_nearest::<1000>(&[0, 0]);
+/// assert!(found.len() == 1000 && found.iter().all(|&(p, d)| p == &[7, 7] && d == 98));
+/// ```
 pub struct KDTree<T, P, const K: usize, const N: usize>
 where
     P: Point<T, K>,
-    T: Ord,
+    T: Coordinate,
 {
     points: [P; N],
-    root: Node,
-    _m: PhantomData<T>,
+    indices: [usize; N],
+    nodes: [Node; N],
+    root: Option<usize>,
+    bounds: Option<Bounds<T, K>>,
+    /// Tight box of every subtree at the position of its node, if asked for.
+    boxes: Option<Box<[Bounds<T, K>]>>,
 }
 
 impl<T, P, const K: usize, const N: usize> From<[P; N]> for KDTree<T, P, K, N>
 where
     P: Point<T, K>,
-    T: Ord,
+    T: Coordinate,
 {
+    /// Builds a balanced tree by recursively splitting every range of points
+    /// at its median along an axis that cycles with depth.
+    ///
+    /// # Panics
+    /// If any coordinate is NaN.
+    /// ```
+    /// use kuina::kd_tree::{KDTree, Point};
+    /// struct P2(f64, f64);
+    /// impl Point<f64, 2> for P2 {
+    ///     fn get(&self, index: usize) -> f64 {
+    ///         [self.0, self.1][index]
+    ///     }
+    /// }
+    /// let tree: KDTree<f64, P2, 2, 3> = [P2(0.5, 1.0), P2(-2.0, 0.0), P2(1.5, -0.5)].into();
+    /// assert_eq!(tree.nearest(&P2(1.0, -1.0)).map(|p| p.0), Some(1.5));
+    /// let nan = std::panic::catch_unwind(|| KDTree::<f64, P2, 2, 1>::from([P2(0.0, f64::NAN)]));
+    /// assert!(nan.is_err());
+    /// ```
     fn from(value: [P; N]) -> Self {
-        let mut points = value;
-        let root = Node {
-            bound_right: N,
-            axis: 0,
-            ..Default::default()
-        };
-        let mut queue = VecDeque::new();
-        queue.push_back(&root);
-        // while let Some(node) = queue.pop_front() {
-        //     points[node.bound_left..node.bound_right].sort_by(|a, b| a.get(0).cmp(&b.get(0)));
-        //     node.index = (node.bound_right - node.bound_left);
-        // }
+        KDTreeBuilder::new().build(value)
+    }
+}
+
+/// Options for building a [`KDTree`].
+/// ```
+/// use kuina::kd_tree::KDTreeBuilder;
+/// let points = [(); 100].map(|_| [0_u8; 2]);
+/// let tree = KDTreeBuilder::new().bucket_size(16).build::<u8, _, 2, 100>(points);
+/// assert_eq!(tree.depth(), 4);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct KDTreeBuilder {
+    bucket_size: usize,
+    split: Split,
+    tight_bounds: bool,
+}
+
+impl Default for KDTreeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KDTreeBuilder {
+    pub fn new() -> Self {
         Self {
+            bucket_size: 1,
+            split: Split::Cycle,
+            tight_bounds: false,
+        }
+    }
+
+    /// Stops splitting ranges of at most `bucket_size` points, queries scan
+    /// such leaves linearly. Larger buckets mean fewer nodes to walk.
+    ///
+    /// # Panics
+    /// If `bucket_size` is zero.
+    /// ```
+    /// use kuina::kd_tree::{KDTree, KDTreeBuilder};
+    /// let points = [[0, 0], [1, 9], [2, 2], [3, 7], [4, 4], [5, 5], [6, 1]];
+    /// let tree: KDTree<i32, _, 2, 7> = KDTreeBuilder::new().bucket_size(4).build(points);
+    /// assert_eq!(tree.depth(), 2);
+    /// assert_eq!(tree.nearest(&[5, 6]), Some(&[5, 5]));
+    /// ```
+    pub fn bucket_size(mut self, bucket_size: usize) -> Self {
+        assert!(bucket_size > 0, "leaf buckets must hold at least one point");
+        self.bucket_size = bucket_size;
+        self
+    }
+
+    /// Picks how every node splits its points, see [`Split`].
+    pub fn split(mut self, split: Split) -> Self {
+        self.split = split;
+        self
+    }
+
+    /// Keeps the tight bounding box of every subtree, so queries skip
+    /// subtrees lying away from the query and not only those behind a split
+    /// plane. The boxes live on the heap, implicit trees keep none.
+    /// ```
+    /// use kuina::kd_tree::{KDTree, KDTreeBuilder};
+    /// let points = [[0, 0], [1, 9], [2, 2], [3, 7], [4, 4], [5, 5], [6, 1]];
+    /// let tree: KDTree<i32, _, 2, 7> = KDTreeBuilder::new().tight_bounds(true).build(points);
+    /// assert_eq!(tree.nearest(&[5, 6]), Some(&[5, 5]));
+    /// assert_eq!(tree.within_radius(&[0, 0], 3).count(), 2);
+    /// ```
+    pub fn tight_bounds(mut self, tight_bounds: bool) -> Self {
+        self.tight_bounds = tight_bounds;
+        self
+    }
+
+    /// # Panics
+    /// If any coordinate is NaN or the split is not [`Split::Cycle`].
+    pub fn build_implicit<T, P, const K: usize, const N: usize>(
+        &self,
+        points: [P; N],
+    ) -> ImplicitKDTree<T, P, K, N>
+    where
+        P: Point<T, K>,
+        T: Coordinate,
+    {
+        self.assert_implicit(&points);
+        let mut points = points;
+        build(&mut points, self, |_| {});
+        ImplicitKDTree {
             points,
+            bucket_size: self.bucket_size,
+            _m: PhantomData,
+        }
+    }
+
+    /// # Panics
+    /// If any coordinate is NaN.
+    pub fn build<T, P, const K: usize, const N: usize>(&self, points: [P; N]) -> KDTree<T, P, K, N>
+    where
+        P: Point<T, K>,
+        T: Coordinate,
+    {
+        assert_no_nan(&points);
+        KDTree::from_indexed(points, self, |points, nodes| {
+            build(points, self, |node| nodes[node.index] = node)
+        })
+    }
+
+    /// Builds the same tree as [`build`](Self::build) straight into a box,
+    /// for trees too large for the stack of a thread.
+    ///
+    /// # Panics
+    /// If any coordinate is NaN.
+    /// ```
+    /// use kuina::kd_tree::KDTreeBuilder;
+    /// let points: Vec<_> = (0..100_000).map(|i| [i % 317, i / 317]).collect();
+    /// let points: Box<[[i32; 2]; 100_000]> = points.into_boxed_slice().try_into().unwrap();
+    /// let tree = KDTreeBuilder::new().build_boxed::<i32, _, 2, 100_000>(points);
+    /// assert_eq!(tree.nearest(&[5, 7]), Some(&[5, 7]));
+    /// ```
+    pub fn build_boxed<T, P, const K: usize, const N: usize>(
+        &self,
+        points: Box<[P; N]>,
+    ) -> Box<KDTree<T, P, K, N>>
+    where
+        P: Point<T, K>,
+        T: Coordinate,
+    {
+        assert_no_nan(&points[..]);
+        KDTree::from_indexed_boxed(points, self, |points, nodes| {
+            build(points, self, |node| nodes[node.index] = node)
+        })
+    }
+
+    /// Checks that an implicit tree can hold `points`.
+    fn assert_implicit<T, P, const K: usize>(&self, points: &[P])
+    where
+        P: Point<T, K>,
+        T: Coordinate,
+    {
+        assert_no_nan(points);
+        assert_eq!(
+            self.split,
+            Split::Cycle,
+            "implicit trees can only cycle axes"
+        );
+    }
+}
+
+impl<T, P, const K: usize, const N: usize> KDTree<T, P, K, N>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    /// ```
+    /// use kuina::kd_tree::{KDTree, Point};
+    /// struct P2(i32, i32);
+    /// impl Point<i32, 2> for P2 {
+    ///     fn get(&self, index: usize) -> i32 {
+    ///         [self.0, self.1][index]
+    ///     }
+    /// }
+    /// let tree: KDTree<i32, P2, 2, 5> = [P2(4, 1), P2(0, 3), P2(2, 2), P2(3, 0), P2(1, 4)].into();
+    /// assert_eq!(tree.points().len(), 5);
+    /// assert_eq!(tree.root().map(|p| p.0), Some(2));
+    /// ```
+    pub fn points(&self) -> &[P] {
+        &self.points
+    }
+
+    /// Position of every point of [`points`](Self::points) in the array the
+    /// tree was built from.
+    /// ```
+    /// use kuina::kd_tree::KDTree;
+    /// let input = [[4, 1], [0, 3], [2, 2], [3, 0], [1, 4]];
+    /// let tree = KDTree::<i32, [i32; 2], 2, 5>::from(input);
+    /// for (point, &index) in tree.points().iter().zip(tree.indices()) {
+    ///     assert_eq!(point, &input[index]);
+    /// }
+    /// ```
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Tags every point with its input position, lets `build` permute them
+    /// and store the nodes, then splits the tags off again.
+    fn from_indexed(
+        points: [P; N],
+        options: &KDTreeBuilder,
+        build: impl FnOnce(&mut [Indexed<P>], &mut [Node]) -> Option<usize>,
+    ) -> Self {
+        let mut index = 0;
+        let mut points = points.map(|point| {
+            index += 1;
+            Indexed {
+                index: index - 1,
+                point,
+            }
+        });
+        let mut nodes = [Node::default(); N];
+        let root = build(&mut points, &mut nodes);
+        let indices = points.each_ref().map(|point| point.index);
+        let points = points.map(|point| point.point);
+        KDTree {
+            bounds: Bounds::of(&points),
+            boxes: options
+                .tight_bounds
+                .then(|| node_bounds(&points, &nodes, root)),
+            points,
+            indices,
+            nodes,
             root,
-            _m: Default::default(),
         }
     }
+
+    /// Same as [`from_indexed`](Self::from_indexed) with every array on the
+    /// heap, the ones of the tree get filled in place.
+    fn from_indexed_boxed(
+        points: Box<[P; N]>,
+        options: &KDTreeBuilder,
+        build: impl FnOnce(&mut [Indexed<P>], &mut [Node]) -> Option<usize>,
+    ) -> Box<Self> {
+        let points = (points as Box<[P]>).into_vec().into_iter().enumerate();
+        let mut points: Vec<_> = points
+            .map(|(index, point)| Indexed { index, point })
+            .collect();
+        let mut nodes = vec![Node::default(); N];
+        let root = build(&mut points, &mut nodes);
+        let bounds = Bounds::of(&points);
+        let boxes = options
+            .tight_bounds
+            .then(|| node_bounds(&points, &nodes, root));
+        let mut tree = Box::<Self>::new_uninit();
+        let slot = tree.as_mut_ptr();
+        // Every field gets written exactly once, the arrays element by
+        // element at positions below `N`.
+        unsafe {
+            let (points_slot, indices_slot) = (
+                (&raw mut (*slot).points).cast::<P>(),
+                (&raw mut (*slot).indices).cast::<usize>(),
+            );
+            for (position, point) in points.into_iter().enumerate() {
+                points_slot.add(position).write(point.point);
+                indices_slot.add(position).write(point.index);
+            }
+            (&raw mut (*slot).nodes)
+                .cast::<Node>()
+                .copy_from_nonoverlapping(nodes.as_ptr(), N);
+            (&raw mut (*slot).root).write(root);
+            (&raw mut (*slot).bounds).write(bounds);
+            (&raw mut (*slot).boxes).write(boxes);
+            tree.assume_init()
+        }
+    }
+
+    /// Smallest box holding every point, `None` for an empty tree.
+    /// ```
+    /// use kuina::kd_tree::KDTree;
+    /// let tree = KDTree::<i32, [i32; 2], 2, 3>::from([[4, 1], [0, 3], [2, -2]]);
+    /// let bounds = tree.bounds().unwrap();
+    /// assert_eq!((bounds.min, bounds.max), ([0, -2], [4, 3]));
+    /// assert_eq!(KDTree::<i32, [i32; 2], 2, 0>::from([]).bounds(), None);
+    /// ```
+    pub fn bounds(&self) -> Option<Bounds<T, K>> {
+        self.bounds
+    }
+
+    fn view(&self) -> View<'_, T, P, K> {
+        View {
+            boxes: self.boxes.as_deref(),
+            ..View::explicit(&self.points, &self.nodes, self.root)
+        }
+    }
+
+    /// Turns positions in `points` of found candidates into positions in the
+    /// input array.
+    fn input_positions<D, const M: usize>(
+        &self,
+        mut found: StackVec<(usize, D), M>,
+    ) -> StackVec<(usize, D), M> {
+        for (index, _) in found.iter_mut() {
+            *index = self.indices[*index];
+        }
+        found
+    }
+
+    pub fn root(&self) -> Option<&P> {
+        self.root.map(|id| &self.points[self.nodes[id].index])
+    }
+
+    /// ```
+    /// use kuina::kd_tree::{KDTree, Point};
+    /// struct P1(u32);
+    /// impl Point<u32, 1> for P1 {
+    ///     fn get(&self, _: usize) -> u32 {
+    ///         self.0
+    ///     }
+    /// }
+    /// let tree = KDTree::<u32, P1, 1, 0>::from([]);
+    /// assert_eq!(tree.depth(), 0);
+    /// let tree = KDTree::<u32, P1, 1, 7>::from([6, 5, 4, 3, 2, 1, 0].map(P1));
+    /// assert_eq!(tree.depth(), 3);
+    /// let tree = KDTree::<u32, P1, 1, 8>::from([7, 6, 5, 4, 3, 2, 1, 0].map(P1));
+    /// assert_eq!(tree.depth(), 4);
+    /// ```
+    pub fn depth(&self) -> usize {
+        fn depth(nodes: &[Node], id: Option<usize>) -> usize {
+            id.map_or(0, |id| {
+                1 + depth(nodes, nodes[id].left).max(depth(nodes, nodes[id].right))
+            })
+        }
+        depth(&self.nodes, self.root)
+    }
+}
+
+impl<T, P, const K: usize, const N: usize> KDTree<T, P, K, N>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    /// Finds the point closest to `query` in squared euclidean distance.
+    /// ```
+    /// use kuina::kd_tree::{KDTree, Point};
+    /// #[derive(Debug, PartialEq)]
+    /// struct P2(i64, i64);
+    /// impl Point<i64, 2> for P2 {
+    ///     fn get(&self, index: usize) -> i64 {
+    ///         [self.0, self.1][index]
+    ///     }
+    /// }
+    /// let tree: KDTree<i64, P2, 2, 4> = [P2(0, 0), P2(10, 0), P2(0, 10), P2(10, 10)].into();
+    /// assert_eq!(tree.nearest(&P2(7, 8)), Some(&P2(10, 10)));
+    /// assert_eq!(KDTree::<i64, P2, 2, 0>::from([]).nearest(&P2(7, 8)), None);
+    /// ```
+    pub fn nearest(&self, query: &P) -> Option<&P> {
+        self.nearest_by(query, &SquaredEuclidean)
+    }
+
+    /// Finds the point closest to `query` in the distance given by `metric`.
+    /// ```
+    /// use kuina::kd_tree::{Chebyshev, KDTree, Manhattan, Point};
+    /// #[derive(Debug, PartialEq)]
+    /// struct P3(i64, i64, i64);
+    /// impl Point<i64, 3> for P3 {
+    ///     fn get(&self, index: usize) -> i64 {
+    ///         [self.0, self.1, self.2][index]
+    ///     }
+    /// }
+    /// let tree: KDTree<i64, P3, 3, 3> = [P3(0, 0, 0), P3(3, 3, 3), P3(5, 0, 0)].into();
+    /// assert_eq!(tree.nearest_by(&P3(3, 0, 0), &Manhattan), Some(&P3(5, 0, 0)));
+    /// assert_eq!(tree.nearest_by(&P3(4, 3, 1), &Chebyshev), Some(&P3(3, 3, 3)));
+    /// ```
+    pub fn nearest_by<D: Metric<T, K>>(&self, query: &P, metric: &D) -> Option<&P> {
+        let mut best = None;
+        self.view().search(query, metric, &mut best);
+        best.map(|(index, _)| &self.points[index])
+    }
+
+    /// Finds a point whose squared euclidean distance to `query` is at most
+    /// `1 + eps` times the one of the closest point. The factor is weighed
+    /// in `f64` whatever the coordinate type, so integer trees take
+    /// fractional `eps` too.
+    /// ```
+    /// use kuina::kd_tree::KDTree;
+    /// let tree = KDTree::<f64, [f64; 2], 2, 3>::from([[0.0, 0.0], [3.0, 0.0], [0.0, 2.5]]);
+    /// assert_eq!(tree.nearest_approx(&[1.4, 1.4], 0.0), Some(&[0.0, 2.5]));
+    /// assert!(tree.nearest_approx(&[1.4, 1.4], 0.5).is_some());
+    /// let tree = KDTree::<i32, [i32; 2], 2, 3>::from([[0, 0], [30, 0], [0, 25]]);
+    /// assert_eq!(tree.nearest_approx(&[14, 14], 0.0), Some(&[0, 25]));
+    /// assert!(tree.nearest_approx(&[14, 14], 0.25).is_some());
+    /// ```
+    pub fn nearest_approx(&self, query: &P, eps: f64) -> Option<&P> {
+        self.nearest_approx_by(query, eps, &SquaredEuclidean)
+    }
+
+    /// Finds a point whose distance to `query` given by `metric` is at most
+    /// `1 + eps` times the one of the closest point.
+    pub fn nearest_approx_by<D: Metric<T, K>>(
+        &self,
+        query: &P,
+        eps: f64,
+        metric: &D,
+    ) -> Option<&P> {
+        let mut limits = Limits {
+            eps,
+            ..Limits::exact()
+        };
+        let mut best = None;
+        self.view()
+            .search_limited(query, metric, &mut limits, &mut best);
+        best.map(|(index, _)| &self.points[index])
+    }
+
+    /// Finds the closest point in squared euclidean distance among the ones
+    /// seen before `max_leaves` leaves are visited. The first leaf is the
+    /// one `query` falls into, so any budget above zero finds some point.
+    /// ```
+    /// use kuina::kd_tree::KDTree;
+    /// let points = [[0, 0], [1, 9], [2, 2], [3, 7], [4, 4], [5, 5], [6, 1]];
+    /// let tree = KDTree::<i32, [i32; 2], 2, 7>::from(points);
+    /// assert_eq!(tree.nearest_budgeted(&[5, 6], 0), None);
+    /// assert!(tree.nearest_budgeted(&[5, 6], 1).is_some());
+    /// assert_eq!(tree.nearest_budgeted(&[5, 6], usize::MAX), tree.nearest(&[5, 6]));
+    /// ```
+    pub fn nearest_budgeted(&self, query: &P, max_leaves: usize) -> Option<&P> {
+        self.nearest_budgeted_by(query, max_leaves, &SquaredEuclidean)
+    }
+
+    /// Finds the closest point in the distance given by `metric` among the
+    /// ones seen before `max_leaves` leaves are visited.
+    pub fn nearest_budgeted_by<D: Metric<T, K>>(
+        &self,
+        query: &P,
+        max_leaves: usize,
+        metric: &D,
+    ) -> Option<&P> {
+        let mut limits = Limits {
+            leaves: max_leaves,
+            ..Limits::exact()
+        };
+        let mut best = None;
+        self.view()
+            .search_limited(query, metric, &mut limits, &mut best);
+        best.map(|(index, _)| &self.points[index])
+    }
+
+    /// Finds the `M` points closest to `query` in squared euclidean distance,
+    /// sorted from the closest one, together with their distances.
+    /// ```
+    /// use kuina::kd_tree::{KDTree, Point};
+    /// #[derive(Debug, PartialEq)]
+    /// struct P2(i64, i64);
+    /// impl Point<i64, 2> for P2 {
+    ///     fn get(&self, index: usize) -> i64 {
+    ///         [self.0, self.1][index]
+    ///     }
+    /// }
+    /// let tree: KDTree<i64, P2, 2, 4> = [P2(0, 0), P2(10, 0), P2(0, 10), P2(10, 10)].into();
+    /// let found = tree.k_nearest::<2>(&P2(7, 8));
+    /// assert_eq!(found, [(&P2(10, 10), 13), (&P2(0, 10), 53)]);
+    /// assert_eq!(tree.k_nearest::<6>(&P2(7, 8)).len(), 4);
+    /// assert!(tree.k_nearest::<0>(&P2(7, 8)).is_empty());
+    /// ```
+    pub fn k_nearest<const M: usize>(&self, query: &P) -> StackVec<(&P, T::Distance), M> {
+        self.k_nearest_by(query, &SquaredEuclidean)
+    }
+
+    /// Finds the `M` points closest to `query` in the distance given by
+    /// `metric`, sorted from the closest one, together with their distances.
+    /// ```
+    /// use kuina::kd_tree::{KDTree, Manhattan, Point};
+    /// #[derive(Debug, PartialEq)]
+    /// struct P2(i64, i64);
+    /// impl Point<i64, 2> for P2 {
+    ///     fn get(&self, index: usize) -> i64 {
+    ///         [self.0, self.1][index]
+    ///     }
+    /// }
+    /// let tree: KDTree<i64, P2, 2, 4> = [P2(0, 0), P2(10, 0), P2(0, 10), P2(10, 10)].into();
+    /// let found = tree.k_nearest_by::<2, _>(&P2(7, 8), &Manhattan);
+    /// assert_eq!(found, [(&P2(10, 10), 5), (&P2(0, 10), 9)]);
+    /// ```
+    pub fn k_nearest_by<const M: usize, D: Metric<T, K>>(
+        &self,
+        query: &P,
+        metric: &D,
+    ) -> StackVec<(&P, T::Distance), M> {
+        k_nearest(
+            |heap| self.view().search(query, metric, heap),
+            |index| &self.points[index],
+        )
+    }
+
+    /// Finds the point closest to `query` in squared euclidean distance
+    /// among the points accepted by `accept`.
+    /// ```
+    /// use kuina::kd_tree::KDTree;
+    /// let tree = KDTree::<i32, [i32; 2], 2, 3>::from([[0, 0], [1, 1], [5, 5]]);
+    /// assert_eq!(tree.nearest_where(&[0, 0], |p| p != &[0, 0]), Some(&[1, 1]));
+    /// assert_eq!(tree.nearest_where(&[0, 0], |p| p[0] > 3), Some(&[5, 5]));
+    /// assert_eq!(tree.nearest_where(&[0, 0], |_| false), None);
+    /// ```
+    pub fn nearest_where(&self, query: &P, accept: impl FnMut(&P) -> bool) -> Option<&P> {
+        self.nearest_where_by(query, &SquaredEuclidean, accept)
+    }
+
+    /// Finds the point closest to `query` in the distance given by `metric`
+    /// among the points accepted by `accept`.
+    pub fn nearest_where_by<D: Metric<T, K>>(
+        &self,
+        query: &P,
+        metric: &D,
+        accept: impl FnMut(&P) -> bool,
+    ) -> Option<&P> {
+        let mut best = None;
+        let mut filtered = Filtered {
+            candidates: &mut best,
+            points: &self.points,
+            accept,
+        };
+        self.view().search(query, metric, &mut filtered);
+        best.map(|(index, _)| &self.points[index])
+    }
+
+    /// Finds the point closest to `query` in squared euclidean distance,
+    /// returns its position in the input array together with its distance.
+    /// ```
+    /// use kuina::kd_tree::KDTree;
+    /// let input = [[0, 0], [10, 0], [3, 4]];
+    /// let tree = KDTree::<i32, [i32; 2], 2, 3>::from(input);
+    /// assert_eq!(tree.nearest_indexed(&[4, 4]), Some((2, 1)));
+    /// assert_eq!(tree.k_nearest_indexed::<2>(&[4, 4]), [(2, 1), (0, 32)]);
+    /// ```
+    pub fn nearest_indexed(&self, query: &P) -> Option<(usize, T::Distance)> {
+        self.nearest_indexed_by(query, &SquaredEuclidean)
+    }
+
+    /// Finds the point closest to `query` in the distance given by `metric`,
+    /// returns its position in the input array together with its distance.
+    pub fn nearest_indexed_by<D: Metric<T, K>>(
+        &self,
+        query: &P,
+        metric: &D,
+    ) -> Option<(usize, T::Distance)> {
+        let mut best = None;
+        self.view().search(query, metric, &mut best);
+        best.map(|(index, distance)| (self.indices[index], distance))
+    }
+
+    /// Finds the `M` points closest to `query` in squared euclidean distance,
+    /// sorted from the closest one, as positions in the input array together
+    /// with their distances.
+    pub fn k_nearest_indexed<const M: usize>(
+        &self,
+        query: &P,
+    ) -> StackVec<(usize, T::Distance), M> {
+        self.k_nearest_indexed_by(query, &SquaredEuclidean)
+    }
+
+    /// Finds the `M` points closest to `query` in the distance given by
+    /// `metric`, sorted from the closest one, as positions in the input array
+    /// together with their distances.
+    pub fn k_nearest_indexed_by<const M: usize, D: Metric<T, K>>(
+        &self,
+        query: &P,
+        metric: &D,
+    ) -> StackVec<(usize, T::Distance), M> {
+        self.input_positions(k_nearest_indices(|heap| {
+            self.view().search(query, metric, heap)
+        }))
+    }
+
+    /// Lazily yields every point whose euclidean distance to `center` is at
+    /// most `radius`. The radius is squared in [`Coordinate::Distance`], so
+    /// it may span the whole coordinate range.
+    ///
+    /// # Panics
+    /// If `radius` is negative.
+    /// ```
+    /// use kuina::kd_tree::{KDTree, Point};
+    /// #[derive(Debug, PartialEq)]
+    /// struct P2(i64, i64);
+    /// impl Point<i64, 2> for P2 {
+    ///     fn get(&self, index: usize) -> i64 {
+    ///         [self.0, self.1][index]
+    ///     }
+    /// }
+    /// let tree: KDTree<i64, P2, 2, 4> = [P2(0, 0), P2(10, 0), P2(0, 10), P2(10, 10)].into();
+    /// let mut found: Vec<_> = tree.within_radius(&P2(5, 0), 5).collect();
+    /// found.sort_by_key(|p| p.0);
+    /// assert_eq!(found, [&P2(0, 0), &P2(10, 0)]);
+    /// assert_eq!(tree.within_radius(&P2(5, 5), 7).next(), None);
+    ///
+    /// let bytes = KDTree::<u8, [u8; 1], 1, 2>::from([[0], [255]]);
+    /// assert_eq!(bytes.within_radius(&[0], 255).count(), 2);
+    /// ```
+    pub fn within_radius<'a>(
+        &'a self,
+        center: &'a P,
+        radius: T,
+    ) -> WithinRadius<'a, T, P, K, SquaredEuclidean> {
+        self.within_radius_by(center, squared(radius), &SquaredEuclidean)
+    }
+
+    /// Lazily yields every point whose distance to `center` given by `metric`
+    /// is at most `radius`, measured in the units of `metric`.
+    /// ```
+    /// use kuina::kd_tree::{Chebyshev, KDTree, Point};
+    /// #[derive(Debug, PartialEq)]
+    /// struct P2(i64, i64);
+    /// impl Point<i64, 2> for P2 {
+    ///     fn get(&self, index: usize) -> i64 {
+    ///         [self.0, self.1][index]
+    ///     }
+    /// }
+    /// let tree: KDTree<i64, P2, 2, 4> = [P2(0, 0), P2(10, 0), P2(0, 10), P2(10, 10)].into();
+    /// let found: Vec<_> = tree.within_radius_by(&P2(5, 5), 5, &Chebyshev).collect();
+    /// assert_eq!(found.len(), 4);
+    /// ```
+    pub fn within_radius_by<'a, D: Metric<T, K>>(
+        &'a self,
+        center: &'a P,
+        radius: T::Distance,
+        metric: &'a D,
+    ) -> WithinRadius<'a, T, P, K, D> {
+        self.view().within_radius(center, radius, metric)
+    }
 }
 
 impl<T, P, const K: usize, const N: usize> KDTree<T, P, K, N>
 where
     P: Point<T, K>,
-    T: Ord,
+    T: Coordinate,
+{
+    /// Lazily yields every point inside the box spanned by `min` and `max`,
+    /// both corners included.
+    /// ```
+    /// use kuina::kd_tree::{KDTree, Point};
+    /// #[derive(Debug, PartialEq)]
+    /// struct P2(i64, i64);
+    /// impl Point<i64, 2> for P2 {
+    ///     fn get(&self, index: usize) -> i64 {
+    ///         [self.0, self.1][index]
+    ///     }
+    /// }
+    /// let tree: KDTree<i64, P2, 2, 4> = [P2(0, 0), P2(10, 0), P2(0, 10), P2(10, 10)].into();
+    /// let mut found: Vec<_> = tree.range(&P2(0, 0), &P2(10, 5)).collect();
+    /// found.sort_by_key(|p| p.0);
+    /// assert_eq!(found, [&P2(0, 0), &P2(10, 0)]);
+    /// assert_eq!(tree.range(&P2(1, 1), &P2(9, 9)).next(), None);
+    /// ```
+    pub fn range<'a>(&'a self, min: &'a P, max: &'a P) -> Range<'a, T, P, K> {
+        self.view().range(min, max)
+    }
+}
+
+impl<T, P, const K: usize, const N: usize> KDTree<T, P, K, N>
+where
+    P: Point<T, K> + MaybeSync,
+    T: Coordinate + MaybeSend + MaybeSync,
+    T::Distance: MaybeSend,
+{
+    /// Finds the point closest to every query in squared euclidean distance,
+    /// answers are positions in the input array like the ones of
+    /// [`nearest_indexed`](Self::nearest_indexed). Queries run in parallel
+    /// with the `rayon` feature.
+    /// ```
+    /// use kuina::kd_tree::KDTree;
+    /// let tree = KDTree::<i32, [i32; 2], 2, 3>::from([[0, 0], [10, 0], [3, 4]]);
+    /// assert_eq!(tree.nearest_batch(&[[9, 1], [1, 1]]), [Some(1), Some(0)]);
+    /// ```
+    pub fn nearest_batch(&self, queries: &[P]) -> Vec<Option<usize>> {
+        self.nearest_batch_by(queries, &SquaredEuclidean)
+    }
+
+    /// Finds the point closest to every query in the distance given by
+    /// `metric`, answers are positions in the input array.
+    pub fn nearest_batch_by<D>(&self, queries: &[P], metric: &D) -> Vec<Option<usize>>
+    where
+        D: Metric<T, K> + MaybeSync,
+    {
+        let view = self.view();
+        batch(queries, |query| {
+            let mut best = None;
+            view.search(query, metric, &mut best);
+            best.map(|(index, _)| self.indices[index])
+        })
+    }
+
+    /// Finds the `M` points closest to every query in squared euclidean
+    /// distance, sorted from the closest one, as positions in the input
+    /// array together with their distances.
+    pub fn k_nearest_batch<const M: usize>(
+        &self,
+        queries: &[P],
+    ) -> Vec<StackVec<(usize, T::Distance), M>> {
+        self.k_nearest_batch_by(queries, &SquaredEuclidean)
+    }
+
+    /// Finds the `M` points closest to every query in the distance given by
+    /// `metric`, sorted from the closest one, as positions in the input
+    /// array together with their distances.
+    pub fn k_nearest_batch_by<const M: usize, D>(
+        &self,
+        queries: &[P],
+        metric: &D,
+    ) -> Vec<StackVec<(usize, T::Distance), M>>
+    where
+        D: Metric<T, K> + MaybeSync,
+    {
+        let view = self.view();
+        batch(queries, |query| {
+            self.input_positions(k_nearest_indices(|heap| view.search(query, metric, heap)))
+        })
+    }
+}
+
+/// Points and nodes of a built tree, every tree type runs its queries on it.
+///
+/// Without `nodes` the tree is implicit: every range is split at its middle
+/// on an axis cycling with depth, nodes get recomputed while walking down.
+struct View<'a, T, P, const K: usize> {
+    points: &'a [P],
+    nodes: Option<&'a [Node]>,
+    root: Option<usize>,
+    bucket_size: usize,
+    /// Tight box of every subtree at the position of its node.
+    boxes: Option<&'a [Bounds<T, K>]>,
+}
+
+impl<T, P, const K: usize> Clone for View<'_, T, P, K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, P, const K: usize> Copy for View<'_, T, P, K> {}
+
+impl<'a, T, P, const K: usize> View<'a, T, P, K>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    fn explicit(points: &'a [P], nodes: &'a [Node], root: Option<usize>) -> Self {
+        Self {
+            points,
+            nodes: Some(nodes),
+            root,
+            bucket_size: 0,
+            boxes: None,
+        }
+    }
+
+    fn implicit(points: &'a [P], bucket_size: usize) -> Self {
+        Self {
+            points,
+            nodes: None,
+            root: Node::slot(0, points.len(), bucket_size),
+            bucket_size,
+            boxes: None,
+        }
+    }
+
+    fn root(&self) -> Option<Node> {
+        self.root.map(|id| match self.nodes {
+            Some(nodes) => nodes[id],
+            None => Node::median(0, self.points.len(), 0, self.bucket_size),
+        })
+    }
+
+    fn children(&self, node: &Node) -> (Option<Node>, Option<Node>) {
+        match self.nodes {
+            Some(nodes) => (
+                node.left.map(|id| nodes[id]),
+                node.right.map(|id| nodes[id]),
+            ),
+            None => node.children((node.axis + 1) % K, self.bucket_size),
+        }
+    }
+
+    fn range(self, min: &'a P, max: &'a P) -> Range<'a, T, P, K> {
+        debug_assert_query(min);
+        debug_assert_query(max);
+        Range {
+            view: self,
+            min,
+            max,
+            stack: self.stack(),
+            bucket: 0..0,
+        }
+    }
+
+    fn within_radius<D: Metric<T, K>>(
+        self,
+        center: &'a P,
+        radius: T::Distance,
+        metric: &'a D,
+    ) -> WithinRadius<'a, T, P, K, D> {
+        debug_assert_query(center);
+        WithinRadius {
+            view: self,
+            center,
+            radius,
+            metric,
+            stack: self.stack(),
+            bucket: 0..0,
+        }
+    }
+
+    /// Finds a point equal to `point` among the ones `accept` lets through.
+    fn position(&self, point: &P, accept: impl Fn(usize) -> bool) -> Option<usize>
+    where
+        P: PartialEq,
+    {
+        let mut stack = self.stack();
+        while let Some(node) = stack.pop() {
+            if node.is_leaf() {
+                if let Some(index) = node
+                    .bucket()
+                    .find(|&index| accept(index) && self.points[index] == *point)
+                {
+                    return Some(index);
+                }
+                continue;
+            }
+            let candidate = &self.points[node.index];
+            if accept(node.index) && candidate == point {
+                return Some(node.index);
+            }
+            let order = at(point, node.axis).total_cmp(&at(candidate, node.axis));
+            let (left, right) = self.children(&node);
+            if let Some(right) = right
+                && order.is_ge()
+            {
+                stack.push(right);
+            }
+            if let Some(left) = left
+                && order.is_le()
+            {
+                stack.push(left);
+            }
+        }
+        None
+    }
+
+    fn stack(&self) -> StackVec<Node, MAX_DEPTH> {
+        let mut stack = StackVec::new();
+        if let Some(root) = self.root() {
+            stack.push(root);
+        }
+        stack
+    }
+
+    fn search(
+        &self,
+        query: &P,
+        metric: &impl Metric<T, K>,
+        candidates: &mut impl Candidates<T::Distance>,
+    ) {
+        self.search_limited(query, metric, &mut Limits::exact(), candidates);
+    }
+
+    fn search_limited(
+        &self,
+        query: &P,
+        metric: &impl Metric<T, K>,
+        limits: &mut Limits,
+        candidates: &mut impl Candidates<T::Distance>,
+    ) {
+        debug_assert_query(query);
+        if let Some(root) = self.root() {
+            self.search_in(&root, query, metric, limits, candidates);
+        }
+    }
+
+    fn search_in(
+        &self,
+        node: &Node,
+        query: &P,
+        metric: &impl Metric<T, K>,
+        limits: &mut Limits,
+        candidates: &mut impl Candidates<T::Distance>,
+    ) {
+        if limits.leaves == 0 {
+            return;
+        }
+        if let Some(boxes) = self.boxes
+            && let Some(best) = candidates.bound()
+        {
+            let bound = boxes[node.index].distance(query, metric);
+            if limits.prunes(bound, best) {
+                return;
+            }
+        }
+        if node.is_leaf() {
+            for index in node.bucket() {
+                self.offer(index, query, metric, candidates);
+            }
+            limits.leaves -= 1;
+            return;
+        }
+        self.offer(node.index, query, metric, candidates);
+        let point = &self.points[node.index];
+        let split = at(point, node.axis);
+        let value = at(query, node.axis);
+        let (left, right) = self.children(node);
+        let (near, far) = if value < split {
+            (left, right)
+        } else {
+            (right, left)
+        };
+        if let Some(near) = near {
+            self.search_in(&near, query, metric, limits, candidates);
+        }
+        if let Some(far) = far {
+            let bound = metric.plane_distance(node.axis, value.abs_diff(split));
+            if candidates
+                .bound()
+                .is_none_or(|best| !limits.prunes(bound, best))
+            {
+                self.search_in(&far, query, metric, limits, candidates);
+            }
+        }
+    }
+
+    fn offer(
+        &self,
+        index: usize,
+        query: &P,
+        metric: &impl Metric<T, K>,
+        candidates: &mut impl Candidates<T::Distance>,
+    ) {
+        let distance = metric.distance(query, &self.points[index]);
+        if candidates.bound().is_none_or(|bound| distance < bound) {
+            candidates.offer(index, distance);
+        }
+    }
+}
+
+/// How much a search may give up on exactness to finish sooner.
+struct Limits {
+    /// Subtrees are skipped unless they can hold a point more than `1 + eps`
+    /// times closer than the best one so far, all of them are searched
+    /// unless it is positive.
+    eps: f64,
+    /// Leaves left to visit before the search stops.
+    leaves: usize,
+}
+
+impl Limits {
+    fn exact() -> Self {
+        Self {
+            eps: 0.0,
+            leaves: usize::MAX,
+        }
+    }
+
+    /// Whether a subtree at least `bound` away can be skipped when the best
+    /// point so far lies `best` away. Distances without an `f64` conversion
+    /// are never skipped approximately.
+    fn prunes<D: Coordinate>(&self, bound: D, best: D) -> bool {
+        if self.eps > 0.0 {
+            bound.to_f64() * (1.0 + self.eps) >= best.to_f64()
+        } else {
+            bound >= best
+        }
+    }
+}
+
+/// Upper bound on the depth of a tree split at medians, it halves the
+/// number of points with every level.
+const MAX_DEPTH: usize = usize::BITS as usize;
+
+pub struct Range<'a, T, P, const K: usize>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    view: View<'a, T, P, K>,
+    min: &'a P,
+    max: &'a P,
+    stack: StackVec<Node, MAX_DEPTH>,
+    bucket: std::ops::Range<usize>,
+}
+
+impl<'a, T, P, const K: usize> Iterator for Range<'a, T, P, K>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    type Item = &'a P;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let inside = |point: &P| {
+            (0..K).all(|axis| {
+                let value = at(point, axis);
+                at(self.min, axis) <= value && value <= at(self.max, axis)
+            })
+        };
+        loop {
+            if let Some(index) = self.bucket.next() {
+                let point = &self.view.points[index];
+                if inside(point) {
+                    return Some(point);
+                }
+                continue;
+            }
+            let node = self.stack.pop()?;
+            if let Some(boxes) = self.view.boxes
+                && !boxes[node.index].intersects(self.min, self.max)
+            {
+                continue;
+            }
+            if node.is_leaf() {
+                self.bucket = node.bucket();
+                continue;
+            }
+            let point = &self.view.points[node.index];
+            let split = at(point, node.axis);
+            let (left, right) = self.view.children(&node);
+            if let Some(right) = right
+                && at(self.max, node.axis) >= split
+            {
+                self.stack.push(right);
+            }
+            if let Some(left) = left
+                && at(self.min, node.axis) <= split
+            {
+                self.stack.push(left);
+            }
+            if inside(point) {
+                return Some(point);
+            }
+        }
+    }
+}
+
+pub struct WithinRadius<'a, T, P, const K: usize, D>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    view: View<'a, T, P, K>,
+    center: &'a P,
+    radius: T::Distance,
+    metric: &'a D,
+    stack: StackVec<Node, MAX_DEPTH>,
+    bucket: std::ops::Range<usize>,
+}
+
+impl<'a, T, P, const K: usize, D> Iterator for WithinRadius<'a, T, P, K, D>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+    D: Metric<T, K>,
+{
+    type Item = &'a P;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(index) = self.bucket.next() {
+                let point = &self.view.points[index];
+                if self.metric.distance(self.center, point) <= self.radius {
+                    return Some(point);
+                }
+                continue;
+            }
+            let node = self.stack.pop()?;
+            if let Some(boxes) = self.view.boxes
+                && boxes[node.index].distance(self.center, self.metric) > self.radius
+            {
+                continue;
+            }
+            if node.is_leaf() {
+                self.bucket = node.bucket();
+                continue;
+            }
+            let point = &self.view.points[node.index];
+            let split = at(point, node.axis);
+            let value = at(self.center, node.axis);
+            let reaches =
+                self.metric.plane_distance(node.axis, value.abs_diff(split)) <= self.radius;
+            let (left, right) = self.view.children(&node);
+            if let Some(right) = right
+                && (value >= split || reaches)
+            {
+                self.stack.push(right);
+            }
+            if let Some(left) = left
+                && (value <= split || reaches)
+            {
+                self.stack.push(left);
+            }
+            if self.metric.distance(self.center, point) <= self.radius {
+                return Some(point);
+            }
+        }
+    }
+}
+
+/// Tight box of every subtree reachable from `root`, at the position of
+/// its node.
+fn node_bounds<T, P, const K: usize>(
+    points: &[P],
+    nodes: &[Node],
+    root: Option<usize>,
+) -> Box<[Bounds<T, K>]>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    let empty = Bounds {
+        min: [T::ZERO; K],
+        max: [T::ZERO; K],
+    };
+    let mut boxes = vec![empty; points.len()];
+    let mut stack = StackVec::<usize, { 2 * MAX_DEPTH }>::new();
+    if let Some(root) = root {
+        stack.push(root);
+    }
+    while let Some(id) = stack.pop() {
+        let node = &nodes[id];
+        boxes[id] = Bounds::of(&points[node.bucket()]).unwrap();
+        for child in [node.left, node.right].into_iter().flatten() {
+            stack.push(child);
+        }
+    }
+    boxes.into_boxed_slice()
+}
+
+/// Runs `search` into a heap of `M` candidates and turns them into points
+/// sorted from the closest one.
+fn k_nearest<'a, T, P, const M: usize>(
+    search: impl FnOnce(&mut StackVec<(usize, T), M>),
+    point: impl Fn(usize) -> &'a P,
+) -> StackVec<(&'a P, T), M>
+where
+    T: Coordinate,
+{
+    let mut found = StackVec::new();
+    for (index, distance) in k_nearest_indices(search) {
+        found.push((point(index), distance));
+    }
+    found
+}
+
+/// Runs `search` into a heap of `M` candidates and sorts them from the
+/// closest one.
+fn k_nearest_indices<T, const M: usize>(
+    search: impl FnOnce(&mut StackVec<(usize, T), M>),
+) -> StackVec<(usize, T), M>
+where
+    T: Coordinate,
+{
+    let mut heap = StackVec::new();
+    if M > 0 {
+        search(&mut heap);
+    }
+    heap.sort_unstable_by(|(_, a), (_, b)| a.total_cmp(b));
+    heap
+}
+
+/// Answers every query into one preallocated vector, in parallel with the
+/// `rayon` feature. Searches need no scratch memory of their own.
+fn batch<Q, R>(queries: &[Q], answer: impl Fn(&Q) -> R + MaybeSend + MaybeSync) -> Vec<R>
+where
+    Q: MaybeSync,
+    R: MaybeSend,
+{
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        queries.par_iter().map(answer).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        queries.iter().map(answer).collect()
+    }
+}
+
+/// Best points found so far by a search, keyed by their position in `points`.
+trait Candidates<T> {
+    /// Distance a point has to beat to be offered, `None` while anything goes.
+    fn bound(&self) -> Option<T>;
+    fn offer(&mut self, index: usize, distance: T);
+}
+
+impl<T: Copy> Candidates<T> for Option<(usize, T)> {
+    fn bound(&self) -> Option<T> {
+        self.map(|(_, distance)| distance)
+    }
+
+    fn offer(&mut self, index: usize, distance: T) {
+        *self = Some((index, distance));
+    }
+}
+
+/// Bounded max-heap on distance, the farthest candidate sits at the front.
+impl<T: Coordinate, const M: usize> Candidates<T> for StackVec<(usize, T), M> {
+    fn bound(&self) -> Option<T> {
+        (self.len() == M).then(|| self[0].1)
+    }
+
+    fn offer(&mut self, index: usize, distance: T) {
+        if self.len() < M {
+            self.push((index, distance));
+            let mut child = self.len() - 1;
+            while child > 0 {
+                let parent = (child - 1) / 2;
+                if self[parent].1 >= self[child].1 {
+                    break;
+                }
+                self.swap(parent, child);
+                child = parent;
+            }
+        } else {
+            self[0] = (index, distance);
+            let mut parent = 0;
+            loop {
+                let left = 2 * parent + 1;
+                let right = left + 1;
+                let mut largest = parent;
+                if left < M && self[left].1 > self[largest].1 {
+                    largest = left;
+                }
+                if right < M && self[right].1 > self[largest].1 {
+                    largest = right;
+                }
+                if largest == parent {
+                    break;
+                }
+                self.swap(parent, largest);
+                parent = largest;
+            }
+        }
+    }
+}
+
+/// Passes on only the points accepted by `accept`, asking it just for
+/// points that would make it into the candidates.
+struct Filtered<'a, C, P, F> {
+    candidates: &'a mut C,
+    points: &'a [P],
+    accept: F,
+}
+
+impl<T, C, P, F> Candidates<T> for Filtered<'_, C, P, F>
+where
+    C: Candidates<T>,
+    F: FnMut(&P) -> bool,
+{
+    fn bound(&self) -> Option<T> {
+        self.candidates.bound()
+    }
+
+    fn offer(&mut self, index: usize, distance: T) {
+        if (self.accept)(&self.points[index]) {
+            self.candidates.offer(index, distance);
+        }
+    }
+}
+
+/// Square of a euclidean `radius`, taken in the distance type so that it
+/// does not overflow.
+fn squared<T: Coordinate>(radius: T) -> T::Distance {
+    assert!(
+        radius >= T::ZERO || radius.is_nan(),
+        "radius must not be negative"
+    );
+    let radius = radius.to_distance();
+    radius.saturating_mul(radius)
+}
+
+fn has_nan<T, P, const K: usize>(point: &P) -> bool
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    (0..K).any(|axis| at(point, axis).is_nan())
+}
+
+fn debug_assert_query<T, P, const K: usize>(query: &P)
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    debug_assert!(
+        !has_nan(query),
+        "KDTree queries must not have NaN coordinates"
+    );
+}
+
+fn assert_no_nan<T, P, const K: usize>(points: &[P])
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    assert!(
+        !points.iter().any(has_nan),
+        "KDTree points must not have NaN coordinates"
+    );
+}
+
+/// Builds a tree by recursively splitting every range of points as the
+/// [`Split`] of `options` says, hands every node to `store` and returns the
+/// position of the root.
+///
+/// Every node sits at the position of a point it owns: the split point of
+/// an inner node, the first point of a leaf. So nodes fit in an array as
+/// long as `points` and a subtree only ever touches its own range.
+fn build<T, P, const K: usize>(
+    points: &mut [P],
+    options: &KDTreeBuilder,
+    store: impl FnMut(Node),
+) -> Option<usize>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    const { assert!(K > 0, "points need at least one dimension") };
+    (!points.is_empty()).then(|| build_subtree(points, 0, 0, 0, options, store))
+}
+
+/// Builds the subtree over the non-empty `points` starting at position
+/// `bound_left`, its root splitting on `axis` if it cycles and lying `depth`
+/// levels deep. Returns the position of the root.
+fn build_subtree<T, P, const K: usize>(
+    points: &mut [P],
+    bound_left: usize,
+    axis: usize,
+    depth: usize,
+    options: &KDTreeBuilder,
+    mut store: impl FnMut(Node),
+) -> usize
+where
+    P: Point<T, K>,
+    T: Coordinate,
 {
-    fn insert(&mut self) {}
+    let root = split_range(points, bound_left, axis, depth, options);
+    let mut stack = StackVec::<_, MAX_DEPTH>::new();
+    stack.push((root, depth));
+    while let Some((mut node, depth)) = stack.pop() {
+        if node.bound_right - node.bound_left > options.bucket_size {
+            let axis = (node.axis + 1) % K;
+            let mut child = |range: std::ops::Range<usize>| {
+                (!range.is_empty()).then(|| {
+                    let points = &mut points[range.start - bound_left..range.end - bound_left];
+                    split_range(points, range.start, axis, depth + 1, options)
+                })
+            };
+            let left = child(node.bound_left..node.index);
+            let right = child(node.index + 1..node.bound_right);
+            node.left = left.map(|left| left.index);
+            node.right = right.map(|right| right.index);
+            if let Some(right) = right {
+                stack.push((right, depth + 1));
+            }
+            if let Some(left) = left {
+                stack.push((left, depth + 1));
+            }
+        }
+        store(node);
+    }
+    root.index
 }
 
-#[derive(Default)]
+/// Subtree over `points[bound_left..bound_right]`. A node with children is
+/// split at the point at `index`, a leaf holds its whole range as a bucket.
+#[derive(Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct Node {
     index: usize,
     bound_left: usize,
@@ -61,3 +1530,50 @@ struct Node {
     left: Option<usize>,
     right: Option<usize>,
 }
+
+impl Node {
+    /// Position of the node over `bound_left..bound_right` when it is split
+    /// at its middle, `None` for an empty range.
+    fn slot(bound_left: usize, bound_right: usize, bucket_size: usize) -> Option<usize> {
+        (bound_left < bound_right).then(|| {
+            if bound_right - bound_left <= bucket_size {
+                bound_left
+            } else {
+                bound_left + (bound_right - bound_left) / 2
+            }
+        })
+    }
+
+    /// Node over the non-empty `bound_left..bound_right` split at its middle.
+    fn median(bound_left: usize, bound_right: usize, axis: usize, bucket_size: usize) -> Self {
+        let index = Self::slot(bound_left, bound_right, bucket_size).unwrap();
+        let is_leaf = bound_right - bound_left <= bucket_size;
+        Self {
+            index,
+            bound_left,
+            bound_right,
+            axis,
+            left: Self::slot(bound_left, index, bucket_size).filter(|_| !is_leaf),
+            right: Self::slot(index + 1, bound_right, bucket_size).filter(|_| !is_leaf),
+        }
+    }
+
+    /// Children of a node split at its middle, themselves split at their
+    /// middle on `axis`.
+    fn children(&self, axis: usize, bucket_size: usize) -> (Option<Self>, Option<Self>) {
+        (
+            self.left
+                .map(|_| Self::median(self.bound_left, self.index, axis, bucket_size)),
+            self.right
+                .map(|_| Self::median(self.index + 1, self.bound_right, axis, bucket_size)),
+        )
+    }
+
+    fn is_leaf(&self) -> bool {
+        self.left.is_none() && self.right.is_none()
+    }
+
+    fn bucket(&self) -> std::ops::Range<usize> {
+        self.bound_left..self.bound_right
+    }
+}
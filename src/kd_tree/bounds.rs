@@ -0,0 +1,105 @@
+use super::{Coordinate, Metric, Point, at};
+
+/// Axis-aligned box, both corners included.
+/// ```
+/// use kuina::kd_tree::Bounds;
+/// let bounds = Bounds::of(&[[1, 5], [3, -2], [2, 0]]).unwrap();
+/// assert_eq!((bounds.min, bounds.max), ([1, -2], [3, 5]));
+/// assert!(bounds.contains(&[3, 0]) && !bounds.contains(&[0, 0]));
+/// assert_eq!(Bounds::<i32, 2>::of::<[i32; 2]>(&[]), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds<T, const K: usize> {
+    pub min: [T; K],
+    pub max: [T; K],
+}
+
+impl<T, const K: usize> Bounds<T, K>
+where
+    T: Coordinate,
+{
+    /// Smallest box holding every point, `None` without points.
+    pub fn of<P: Point<T, K>>(points: &[P]) -> Option<Self> {
+        let (first, rest) = points.split_first()?;
+        let mut bounds = Self::point(first);
+        for point in rest {
+            bounds.add(point);
+        }
+        Some(bounds)
+    }
+
+    pub fn contains<P: Point<T, K>>(&self, point: &P) -> bool {
+        (0..K).all(|axis| {
+            let value = at(point, axis);
+            self.min[axis] <= value && value <= self.max[axis]
+        })
+    }
+
+    /// Box holding just `point`.
+    pub(super) fn point<P: Point<T, K>>(point: &P) -> Self {
+        let corner = std::array::from_fn(|axis| at(point, axis));
+        Self {
+            min: corner,
+            max: corner,
+        }
+    }
+
+    /// Grows the box to hold `point`.
+    pub(super) fn add<P: Point<T, K>>(&mut self, point: &P) {
+        for axis in 0..K {
+            let value = at(point, axis);
+            if value < self.min[axis] {
+                self.min[axis] = value;
+            }
+            if value > self.max[axis] {
+                self.max[axis] = value;
+            }
+        }
+    }
+
+    /// Whether the box shares any point with the one spanned by `min` and
+    /// `max`.
+    pub(super) fn intersects<P: Point<T, K>>(&self, min: &P, max: &P) -> bool {
+        (0..K).all(|axis| at(min, axis) <= self.max[axis] && self.min[axis] <= at(max, axis))
+    }
+
+    /// Lower bound on the distance given by `metric` from `query` to any
+    /// point in the box.
+    pub(super) fn distance<P, D>(&self, query: &P, metric: &D) -> T::Distance
+    where
+        P: Point<T, K>,
+        D: Metric<T, K>,
+    {
+        metric.box_distance(std::array::from_fn(|axis| {
+            let value = at(query, axis);
+            if value < self.min[axis] {
+                self.min[axis].abs_diff(value)
+            } else if value > self.max[axis] {
+                value.abs_diff(self.max[axis])
+            } else {
+                T::Distance::ZERO
+            }
+        }))
+    }
+
+    /// Lower bound on the distance given by `metric` between any point in
+    /// the box and any point in `other`.
+    pub(super) fn separation<D: Metric<T, K>>(&self, other: &Self, metric: &D) -> T::Distance {
+        metric.box_distance(std::array::from_fn(|axis| {
+            if other.max[axis] < self.min[axis] {
+                self.min[axis].abs_diff(other.max[axis])
+            } else if other.min[axis] > self.max[axis] {
+                other.min[axis].abs_diff(self.max[axis])
+            } else {
+                T::Distance::ZERO
+            }
+        }))
+    }
+
+    /// Sum of the side lengths.
+    pub(super) fn extent(&self) -> f64 {
+        (0..K)
+            .map(|axis| self.max[axis].abs_diff(self.min[axis]).to_f64())
+            .sum()
+    }
+}
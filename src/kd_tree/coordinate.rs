@@ -0,0 +1,168 @@
+use std::{
+    cmp::Ordering,
+    ops::{Add, Mul, Sub},
+};
+
+/// Scalar type a point coordinate can have, implemented for all primitive
+/// integers and floats.
+pub trait Coordinate:
+    Copy + PartialOrd + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self>
+{
+    /// Type distances between coordinates are measured in. For integers it
+    /// is unsigned and twice as wide as `Self`, so it holds the squared
+    /// difference of any two of them. Sums over several axes saturate at its
+    /// largest value instead of overflowing.
+    /// ```
+    /// use kuina::kd_tree::{KDTree, Metric, SquaredEuclidean};
+    /// assert_eq!(SquaredEuclidean.distance(&[-70_000_i32], &[0]), 4_900_000_000_u64);
+    /// let tree = KDTree::<u8, [u8; 2], 2, 2>::from([[0, 0], [255, 255]]);
+    /// assert_eq!(tree.k_nearest::<2>(&[0, 0])[1].1, 130_050_u32);
+    /// let far = SquaredEuclidean.distance(&[i64::MIN, i64::MIN], &[i64::MAX, i64::MAX]);
+    /// assert_eq!(far, u128::MAX);
+    /// ```
+    type Distance: Coordinate;
+
+    const ZERO: Self;
+
+    /// Total order used to split points, for floats it is the IEEE 754
+    /// `totalOrder` predicate.
+    /// ```
+    /// use kuina::kd_tree::Coordinate;
+    /// use std::cmp::Ordering;
+    /// assert_eq!(Coordinate::total_cmp(&-0.0, &0.0), Ordering::Less);
+    /// assert_eq!(Coordinate::total_cmp(&3_u8, &3), Ordering::Equal);
+    /// ```
+    fn total_cmp(&self, other: &Self) -> Ordering;
+
+    /// Lossy conversion for heuristics weighing distances against counts.
+    /// It is NaN unless overridden, [`Split::SurfaceArea`](super::Split)
+    /// then falls back to medians for points measured in this type.
+    /// ```
+    /// use kuina::kd_tree::Coordinate;
+    /// assert_eq!(Coordinate::to_f64(3_u8), 3.0);
+    /// ```
+    fn to_f64(self) -> f64 {
+        f64::NAN
+    }
+
+    /// ```
+    /// use kuina::kd_tree::Coordinate;
+    /// assert!(Coordinate::is_nan(&f32::NAN));
+    /// assert!(!Coordinate::is_nan(&1));
+    /// ```
+    fn is_nan(&self) -> bool {
+        false
+    }
+
+    /// Conversion to the distance type, lossless for values that are not
+    /// negative. Negative integers become zero.
+    /// ```
+    /// use kuina::kd_tree::Coordinate;
+    /// assert_eq!(Coordinate::to_distance(i32::MAX), 2_147_483_647_u64);
+    /// assert_eq!(Coordinate::to_distance(-3_i8), 0);
+    /// ```
+    fn to_distance(self) -> Self::Distance;
+
+    /// Distance between two coordinates in the distance type, it neither
+    /// underflows nor overflows for any two integers.
+    /// ```
+    /// use kuina::kd_tree::Coordinate;
+    /// assert_eq!(Coordinate::abs_diff(3_u32, 5), 2);
+    /// assert_eq!(Coordinate::abs_diff(-100_i8, 100), 200);
+    /// assert_eq!(Coordinate::abs_diff(i128::MIN, i128::MAX), u128::MAX);
+    /// assert_eq!(Coordinate::abs_diff(-1.5, 1.0), 2.5);
+    /// ```
+    fn abs_diff(self, other: Self) -> Self::Distance {
+        let (a, b) = (self.to_distance(), other.to_distance());
+        if a < b { b - a } else { a - b }
+    }
+
+    /// Sum that stays at the largest value of an integer type instead of
+    /// overflowing, plain addition unless overridden.
+    /// ```
+    /// use kuina::kd_tree::Coordinate;
+    /// assert_eq!(Coordinate::saturating_add(250_u8, 10), 255);
+    /// assert_eq!(Coordinate::saturating_add(0.5, 0.25), 0.75);
+    /// ```
+    fn saturating_add(self, other: Self) -> Self {
+        self + other
+    }
+
+    /// Product that stays at the largest value of an integer type instead
+    /// of overflowing, plain multiplication unless overridden.
+    /// ```
+    /// use kuina::kd_tree::Coordinate;
+    /// assert_eq!(Coordinate::saturating_mul(u64::MAX, 2), u64::MAX);
+    /// assert_eq!(Coordinate::saturating_mul(0.5, 3.0), 1.5);
+    /// ```
+    fn saturating_mul(self, other: Self) -> Self {
+        self * other
+    }
+}
+
+macro_rules! impl_integer {
+    ($($t:ty => $d:ty)*) => {
+        $(impl Coordinate for $t {
+            type Distance = $d;
+
+            const ZERO: Self = 0;
+
+            fn total_cmp(&self, other: &Self) -> Ordering {
+                self.cmp(other)
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn to_distance(self) -> $d {
+                <$d>::try_from(self).unwrap_or(0)
+            }
+
+            fn abs_diff(self, other: Self) -> $d {
+                <$t>::abs_diff(self, other) as $d
+            }
+
+            fn saturating_add(self, other: Self) -> Self {
+                <$t>::saturating_add(self, other)
+            }
+
+            fn saturating_mul(self, other: Self) -> Self {
+                <$t>::saturating_mul(self, other)
+            }
+        })*
+    }
+}
+
+macro_rules! impl_float {
+    ($($t:ty)*) => {
+        $(impl Coordinate for $t {
+            type Distance = Self;
+
+            const ZERO: Self = 0.0;
+
+            fn total_cmp(&self, other: &Self) -> Ordering {
+                <$t>::total_cmp(self, other)
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn to_distance(self) -> Self {
+                self
+            }
+
+            fn is_nan(&self) -> bool {
+                <$t>::is_nan(*self)
+            }
+        })*
+    }
+}
+
+impl_integer! {
+    i8 => u32 i16 => u64 i32 => u64 i64 => u128 i128 => u128
+    u8 => u32 u16 => u64 u32 => u64 u64 => u128 u128 => u128
+    isize => u128 usize => u128
+}
+impl_float! { f32 f64 }
@@ -0,0 +1,437 @@
+use std::ops::Range;
+
+use crate::stack_vec::StackVec;
+
+use super::{
+    Bounds, Coordinate, KDTree, Limits, MAX_DEPTH, Metric, Node, Point, SquaredEuclidean, View, at,
+    squared,
+};
+
+/// Subtree on one side of a dual traversal with a box holding its points.
+#[derive(Clone, Copy)]
+struct Cell<T, const K: usize> {
+    node: Node,
+    bounds: Bounds<T, K>,
+}
+
+impl<T, const K: usize> Cell<T, K> {
+    fn len(&self) -> usize {
+        self.node.bound_right - self.node.bound_left
+    }
+}
+
+/// Pairs of cells left to visit. Every split leaves at most two more and
+/// each side splits fewer than `MAX_DEPTH` times along a path, splitting a
+/// cell paired with itself leaves five more.
+type Pairs<T, const K: usize> = StackVec<(Cell<T, K>, Cell<T, K>), { 5 * MAX_DEPTH }>;
+
+fn root<T, P, const K: usize, const N: usize>(tree: &KDTree<T, P, K, N>) -> Option<Cell<T, K>>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    Some(Cell {
+        node: tree.nodes[tree.root?],
+        bounds: tree.bounds?,
+    })
+}
+
+/// Splits an inner cell into a leaf holding just its own point and its
+/// subtrees. Subtrees take their tight box when the tree keeps them and the
+/// box of `cell` cut at the split plane otherwise.
+fn split<T, P, const K: usize>(
+    view: &View<'_, T, P, K>,
+    cell: &Cell<T, K>,
+) -> [Option<Cell<T, K>>; 3]
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    let node = cell.node;
+    let point = &view.points[node.index];
+    let own = Cell {
+        node: Node {
+            bound_left: node.index,
+            bound_right: node.index + 1,
+            left: None,
+            right: None,
+            ..node
+        },
+        bounds: Bounds::point(point),
+    };
+    let split = at(point, node.axis);
+    let child = |child: Node| {
+        let bounds = match view.boxes {
+            Some(boxes) => boxes[child.index],
+            None => {
+                let mut bounds = cell.bounds;
+                if child.bound_right <= node.index {
+                    bounds.max[node.axis] = split;
+                } else {
+                    bounds.min[node.axis] = split;
+                }
+                bounds
+            }
+        };
+        Cell {
+            node: child,
+            bounds,
+        }
+    };
+    let (left, right) = view.children(&node);
+    [Some(own), left.map(child), right.map(child)]
+}
+
+/// Whether a traversal goes on by splitting `a` rather than `b`, the larger
+/// of the two unless it is a leaf.
+fn splits_first<T, const K: usize>(a: &Cell<T, K>, b: &Cell<T, K>) -> bool {
+    !a.node.is_leaf() && (b.node.is_leaf() || a.len() >= b.len())
+}
+
+/// Largest of `limits`, `None` if any of them is.
+fn loosest<T: Coordinate>(limits: impl IntoIterator<Item = Option<T>>) -> Option<T> {
+    limits.into_iter().try_fold(T::ZERO, |max, limit| {
+        limit.map(|limit| if limit > max { limit } else { max })
+    })
+}
+
+impl<T, P, const K: usize, const N: usize> KDTree<T, P, K, N>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    /// Finds the point of `other` closest to every point of `self` in
+    /// squared euclidean distance. The answer for the point at position `i`
+    /// of the input array of `self` sits at `i` and holds a position in the
+    /// input array of `other` with its distance, `None` only when `other` is
+    /// empty.
+    ///
+    /// Both trees are walked together, so a whole subtree of `self` drops a
+    /// subtree of `other` at once. The limits of the subtrees of `self` are
+    /// kept in one heap allocated slot per point.
+    /// ```
+    /// use kuina::kd_tree::KDTree;
+    /// let tree = KDTree::<i32, [i32; 2], 2, 2>::from([[0, 0], [10, 10]]);
+    /// let other = KDTree::<i32, [i32; 2], 2, 3>::from([[1, 0], [9, 9], [20, 20]]);
+    /// assert_eq!(tree.all_nearest(&other), [Some((0, 1)), Some((1, 2))]);
+    /// let empty = KDTree::<i32, [i32; 2], 2, 0>::from([]);
+    /// assert_eq!(tree.all_nearest(&empty), [None, None]);
+    /// ```
+    pub fn all_nearest<const M: usize>(
+        &self,
+        other: &KDTree<T, P, K, M>,
+    ) -> [Option<(usize, T::Distance)>; N] {
+        self.all_nearest_by(other, &SquaredEuclidean)
+    }
+
+    /// Finds the point of `other` closest to every point of `self` in the
+    /// distance given by `metric`, laid out like
+    /// [`all_nearest`](Self::all_nearest).
+    pub fn all_nearest_by<const M: usize, D: Metric<T, K>>(
+        &self,
+        other: &KDTree<T, P, K, M>,
+        metric: &D,
+    ) -> [Option<(usize, T::Distance)>; N] {
+        let mut found = [None; N];
+        let (Some(queries), Some(references)) = (root(self), root(other)) else {
+            return found;
+        };
+        let (view, other_view) = (self.view(), other.view());
+        // Largest distance found under every inner node, `None` while some
+        // point under it has none. Entries only ever lag behind, which is safe.
+        let mut limits = vec![None; N];
+        let mut stack = Pairs::new();
+        stack.push((queries, references));
+        while let Some((query, reference)) = stack.pop() {
+            let bucket = |node: &Node| loosest(node.bucket().map(|i| found[i].map(|(_, d)| d)));
+            let limit = if query.node.is_leaf() {
+                bucket(&query.node)
+            } else {
+                let (left, right) = view.children(&query.node);
+                let children = [left, right].into_iter().flatten().map(|child| {
+                    if child.is_leaf() {
+                        bucket(&child)
+                    } else {
+                        limits[child.index]
+                    }
+                });
+                let own = found[query.node.index].map(|(_, d)| d);
+                let limit = loosest(children.chain([own]));
+                limits[query.node.index] = limit;
+                limit
+            };
+            if let Some(limit) = limit
+                && query.bounds.separation(&reference.bounds, metric) >= limit
+            {
+                continue;
+            }
+            if query.node.is_leaf() {
+                for index in query.node.bucket() {
+                    let point = &self.points[index];
+                    let limits = &mut Limits::exact();
+                    other_view.search_in(&reference.node, point, metric, limits, &mut found[index]);
+                }
+            } else if splits_first(&query, &reference) {
+                for cell in split(&view, &query).into_iter().flatten() {
+                    stack.push((cell, reference));
+                }
+            } else {
+                let [own, left, right] = split(&other_view, &reference);
+                let separation = |cell: &Option<Cell<T, K>>| {
+                    cell.map(|cell| query.bounds.separation(&cell.bounds, metric))
+                };
+                let (near, far) = if separation(&right) < separation(&left) {
+                    (right, left)
+                } else {
+                    (left, right)
+                };
+                for cell in [own, far, near].into_iter().flatten() {
+                    stack.push((query, cell));
+                }
+            }
+        }
+        let mut answers = [None; N];
+        for (index, found) in found.into_iter().enumerate() {
+            answers[self.indices[index]] = found.map(|(index, d)| (other.indices[index], d));
+        }
+        answers
+    }
+
+    /// Lazily yields every pair of a position in the input array of `self`
+    /// and one in the input array of `other` whose points lie at most
+    /// `radius` apart in euclidean distance, walking both trees together.
+    /// The radius gets squared like in [`within_radius`](Self::within_radius).
+    ///
+    /// # Panics
+    /// If `radius` is negative.
+    /// ```
+    /// use kuina::kd_tree::KDTree;
+    /// let tree = KDTree::<i32, [i32; 2], 2, 2>::from([[0, 0], [10, 10]]);
+    /// let other = KDTree::<i32, [i32; 2], 2, 3>::from([[1, 0], [9, 9], [20, 20]]);
+    /// let mut pairs: Vec<_> = tree.join_within(&other, 2).collect();
+    /// pairs.sort();
+    /// assert_eq!(pairs, [(0, 0), (1, 1)]);
+    /// ```
+    pub fn join_within<'a, const M: usize>(
+        &'a self,
+        other: &'a KDTree<T, P, K, M>,
+        radius: T,
+    ) -> JoinWithin<'a, T, P, K, SquaredEuclidean> {
+        self.join_within_by(other, squared(radius), &SquaredEuclidean)
+    }
+
+    /// Lazily yields every pair of input positions like
+    /// [`join_within`](Self::join_within) whose points lie at most `radius`
+    /// apart in the distance given by `metric`, measured in its units.
+    pub fn join_within_by<'a, const M: usize, D: Metric<T, K>>(
+        &'a self,
+        other: &'a KDTree<T, P, K, M>,
+        radius: T::Distance,
+        metric: &'a D,
+    ) -> JoinWithin<'a, T, P, K, D> {
+        let roots = root(self).zip(root(other));
+        let (left, right) = (
+            (self.view(), &self.indices[..]),
+            (other.view(), &other.indices[..]),
+        );
+        JoinWithin::new(left, right, roots, radius, metric, false)
+    }
+
+    /// Lazily yields every unordered pair of positions in the input array
+    /// whose points lie closer than `radius` in euclidean distance, exactly
+    /// once and smaller position first. Points exactly `radius` apart do not
+    /// pair up, unlike in [`join_within`](Self::join_within). No point pairs
+    /// with itself, equal points at different positions do.
+    ///
+    /// # Panics
+    /// If `radius` is negative.
+    /// ```
+    /// use kuina::kd_tree::KDTree;
+    /// let tree = KDTree::<i32, [i32; 2], 2, 4>::from([[0, 0], [1, 0], [0, 0], [5, 5]]);
+    /// let mut pairs: Vec<_> = tree.pairs_within(2).collect();
+    /// pairs.sort();
+    /// assert_eq!(pairs, [(0, 1), (0, 2), (1, 2)]);
+    /// assert_eq!(tree.pairs_within(1).collect::<Vec<_>>(), [(0, 2)]);
+    /// ```
+    pub fn pairs_within(&self, radius: T) -> PairsWithin<'_, T, P, K, SquaredEuclidean> {
+        self.pairs_within_by(squared(radius), &SquaredEuclidean)
+    }
+
+    /// Lazily yields every unordered pair of positions in the input array
+    /// whose points lie closer than `radius` in the distance given by
+    /// `metric`, measured in its units, like
+    /// [`pairs_within`](Self::pairs_within).
+    pub fn pairs_within_by<'a, D: Metric<T, K>>(
+        &'a self,
+        radius: T::Distance,
+        metric: &'a D,
+    ) -> PairsWithin<'a, T, P, K, D> {
+        let roots = root(self).map(|cell| (cell, cell));
+        let side = (self.view(), &self.indices[..]);
+        PairsWithin(JoinWithin::new(side, side, roots, radius, metric, true))
+    }
+}
+
+/// Lazily yields pairs of input positions of the points of two trees lying
+/// within a radius of each other, see [`KDTree::join_within`].
+pub struct JoinWithin<'a, T, P, const K: usize, D>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+    D: Metric<T, K>,
+{
+    left: View<'a, T, P, K>,
+    right: View<'a, T, P, K>,
+    /// Position in the input array of every point on each side.
+    left_indices: &'a [usize],
+    right_indices: &'a [usize],
+    radius: T::Distance,
+    metric: &'a D,
+    stack: Pairs<T, K>,
+    /// Whether both sides are the same tree, whose cells pair up with
+    /// themselves and later cells only.
+    self_join: bool,
+    /// Leaf buckets being paired up: the rows left, the whole bucket of
+    /// columns and the columns left in the first row. Diagonal buckets pair
+    /// a leaf with itself, rows only take the columns after them.
+    rows: Range<usize>,
+    bucket: Range<usize>,
+    columns: Range<usize>,
+    diagonal: bool,
+}
+
+impl<'a, T, P, const K: usize, D> JoinWithin<'a, T, P, K, D>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+    D: Metric<T, K>,
+{
+    fn new(
+        (left, left_indices): (View<'a, T, P, K>, &'a [usize]),
+        (right, right_indices): (View<'a, T, P, K>, &'a [usize]),
+        roots: Option<(Cell<T, K>, Cell<T, K>)>,
+        radius: T::Distance,
+        metric: &'a D,
+        self_join: bool,
+    ) -> Self {
+        let mut stack = Pairs::new();
+        if let Some(roots) = roots {
+            stack.push(roots);
+        }
+        Self {
+            left,
+            right,
+            left_indices,
+            right_indices,
+            radius,
+            metric,
+            stack,
+            self_join,
+            rows: 0..0,
+            bucket: 0..0,
+            columns: 0..0,
+            diagonal: false,
+        }
+    }
+
+    /// Whether points `distance` apart pair up, self-joins leave out the ones
+    /// exactly `radius` apart.
+    fn close_enough(&self, distance: T::Distance) -> bool {
+        if self.self_join {
+            distance < self.radius
+        } else {
+            distance <= self.radius
+        }
+    }
+}
+
+impl<T, P, const K: usize, D> Iterator for JoinWithin<'_, T, P, K, D>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+    D: Metric<T, K>,
+{
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if !self.rows.is_empty() {
+                let Some(column) = self.columns.next() else {
+                    self.rows.start += 1;
+                    self.columns = if self.diagonal {
+                        self.rows.start + 1..self.bucket.end
+                    } else {
+                        self.bucket.clone()
+                    };
+                    continue;
+                };
+                let row = self.rows.start;
+                let (a, b) = (&self.left.points[row], &self.right.points[column]);
+                if self.close_enough(self.metric.distance(a, b)) {
+                    let (row, column) = (self.left_indices[row], self.right_indices[column]);
+                    if self.self_join && column < row {
+                        return Some((column, row));
+                    }
+                    return Some((row, column));
+                }
+                continue;
+            }
+            let (left, right) = self.stack.pop()?;
+            if !self.close_enough(left.bounds.separation(&right.bounds, self.metric)) {
+                continue;
+            }
+            if self.self_join && left.node.bucket() == right.node.bucket() {
+                if left.node.is_leaf() {
+                    self.rows = left.node.bucket();
+                    self.bucket = left.node.bucket();
+                    self.columns = self.rows.start + 1..self.bucket.end;
+                    self.diagonal = true;
+                    continue;
+                }
+                let cells = split(&self.left, &left);
+                for (i, a) in cells.iter().enumerate() {
+                    for b in &cells[i..] {
+                        if let (Some(a), Some(b)) = (a, b) {
+                            self.stack.push((*a, *b));
+                        }
+                    }
+                }
+            } else if left.node.is_leaf() && right.node.is_leaf() {
+                self.rows = left.node.bucket();
+                self.bucket = right.node.bucket();
+                self.columns = self.bucket.clone();
+                self.diagonal = false;
+            } else if splits_first(&left, &right) {
+                for cell in split(&self.left, &left).into_iter().flatten() {
+                    self.stack.push((cell, right));
+                }
+            } else {
+                for cell in split(&self.right, &right).into_iter().flatten() {
+                    self.stack.push((left, cell));
+                }
+            }
+        }
+    }
+}
+
+/// Lazily yields unordered pairs of input positions of the points of one
+/// tree lying closer than a radius to each other, see
+/// [`KDTree::pairs_within`].
+pub struct PairsWithin<'a, T, P, const K: usize, D>(JoinWithin<'a, T, P, K, D>)
+where
+    P: Point<T, K>,
+    T: Coordinate,
+    D: Metric<T, K>;
+
+impl<T, P, const K: usize, D> Iterator for PairsWithin<'_, T, P, K, D>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+    D: Metric<T, K>,
+{
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
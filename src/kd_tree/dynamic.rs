@@ -0,0 +1,315 @@
+use std::marker::PhantomData;
+
+use crate::stack_vec::StackVec;
+
+use super::{
+    Candidates, Coordinate, KDTreeBuilder, Metric, Node, Point, SquaredEuclidean, View,
+    assert_no_nan, build, k_nearest,
+};
+
+/// Heap backed tree that supports insertion and removal.
+///
+/// Points live in a logarithmic forest: level `i` holds a static tree of at
+/// most `2^i` points. Inserting merges the full levels below the first free
+/// one into it, like a carry in a binary counter. Removed points are only
+/// marked and get dropped once they outnumber the remaining ones.
+/// ```
+/// use kuina::kd_tree::DynamicKDTree;
+/// let mut tree = DynamicKDTree::<i64, [i64; 2], 2>::new();
+/// tree.insert([0, 0]);
+/// tree.insert([10, 10]);
+/// tree.insert([3, 4]);
+/// assert_eq!(tree.len(), 3);
+/// assert_eq!(tree.nearest(&[9, 9]), Some(&[10, 10]));
+/// assert!(tree.remove(&[10, 10]));
+/// assert!(!tree.remove(&[10, 10]));
+/// assert_eq!(tree.nearest(&[9, 9]), Some(&[3, 4]));
+/// ```
+pub struct DynamicKDTree<T, P, const K: usize>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    levels: Vec<Option<Level<P>>>,
+    len: usize,
+    removed: usize,
+    _m: PhantomData<T>,
+}
+
+struct Level<P> {
+    points: Vec<P>,
+    nodes: Vec<Node>,
+    root: Option<usize>,
+    removed: Vec<bool>,
+    len: usize,
+}
+
+impl<T, P, const K: usize> Default for DynamicKDTree<T, P, K>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, P, const K: usize> DynamicKDTree<T, P, K>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    pub fn new() -> Self {
+        Self {
+            levels: Vec::new(),
+            len: 0,
+            removed: 0,
+            _m: PhantomData,
+        }
+    }
+
+    /// Number of points in the tree, removed ones are not counted.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// ```
+    /// use kuina::kd_tree::DynamicKDTree;
+    /// let mut tree = DynamicKDTree::<u8, (u8, u8), 2>::new();
+    /// tree.insert((1, 2));
+    /// tree.insert((3, 4));
+    /// tree.remove(&(1, 2));
+    /// assert_eq!(tree.iter().collect::<Vec<_>>(), [&(3, 4)]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = &P> {
+        self.levels.iter().flatten().flat_map(|level| {
+            level
+                .points
+                .iter()
+                .zip(&level.removed)
+                .filter_map(|(point, &removed)| (!removed).then_some(point))
+        })
+    }
+
+    fn point(&self, index: usize) -> &P {
+        let level = (index + 1).ilog2() as usize;
+        let level_index = index + 1 - (1 << level);
+        let level = self.levels[level].as_ref().unwrap();
+        &level.points[level_index]
+    }
+}
+
+impl<T, P, const K: usize> DynamicKDTree<T, P, K>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    /// # Panics
+    /// If any coordinate is NaN.
+    pub fn insert(&mut self, point: P) {
+        assert_no_nan(std::slice::from_ref(&point));
+        let mut points = vec![point];
+        let mut level = 0;
+        while let Some(Some(full)) = self.levels.get_mut(level).map(Option::take) {
+            self.removed -= full.removed.len() - full.len;
+            points.extend(full.into_points());
+            level += 1;
+        }
+        if level == self.levels.len() {
+            self.levels.push(None);
+        }
+        self.levels[level] = Some(Level::new(points));
+        self.len += 1;
+    }
+
+    /// Finds the point closest to `query` in squared euclidean distance.
+    pub fn nearest(&self, query: &P) -> Option<&P> {
+        self.nearest_by(query, &SquaredEuclidean)
+    }
+
+    /// Finds the point closest to `query` in the distance given by `metric`.
+    pub fn nearest_by<D: Metric<T, K>>(&self, query: &P, metric: &D) -> Option<&P> {
+        let mut best = None;
+        self.search(query, metric, &mut best);
+        best.map(|(index, _)| self.point(index))
+    }
+
+    /// Finds the `M` points closest to `query` in squared euclidean distance,
+    /// sorted from the closest one, together with their distances.
+    /// ```
+    /// use kuina::kd_tree::DynamicKDTree;
+    /// let mut tree = DynamicKDTree::<i32, [i32; 1], 1>::new();
+    /// for x in [5, 1, 9, 3, 7] {
+    ///     tree.insert([x]);
+    /// }
+    /// tree.remove(&[3]);
+    /// assert_eq!(tree.k_nearest::<2>(&[5]), [(&[5], 0), (&[7], 4)]);
+    /// ```
+    pub fn k_nearest<const M: usize>(&self, query: &P) -> StackVec<(&P, T::Distance), M> {
+        self.k_nearest_by(query, &SquaredEuclidean)
+    }
+
+    /// Finds the `M` points closest to `query` in the distance given by
+    /// `metric`, sorted from the closest one, together with their distances.
+    pub fn k_nearest_by<const M: usize, D: Metric<T, K>>(
+        &self,
+        query: &P,
+        metric: &D,
+    ) -> StackVec<(&P, T::Distance), M> {
+        k_nearest(
+            |heap| self.search(query, metric, heap),
+            |index| self.point(index),
+        )
+    }
+
+    /// Searches every level into the same candidates, a point at `index` of
+    /// level `i` is offered as `2^i - 1 + index`.
+    fn search(
+        &self,
+        query: &P,
+        metric: &impl Metric<T, K>,
+        candidates: &mut impl Candidates<T::Distance>,
+    ) {
+        for (level, points) in self.levels.iter().enumerate() {
+            if let Some(points) = points {
+                let mut live = Live {
+                    candidates: &mut *candidates,
+                    removed: &points.removed,
+                    offset: (1 << level) - 1,
+                };
+                points.view::<T, K>().search(query, metric, &mut live);
+            }
+        }
+    }
+}
+
+impl<T, P, const K: usize> DynamicKDTree<T, P, K>
+where
+    P: Point<T, K> + PartialEq,
+    T: Coordinate,
+{
+    /// Removes one point equal to `point`, returns whether there was one.
+    /// ```
+    /// use kuina::kd_tree::DynamicKDTree;
+    /// let mut tree = DynamicKDTree::<i32, [i32; 2], 2>::new();
+    /// for x in 0..100 {
+    ///     tree.insert([x, x % 7]);
+    ///     tree.insert([x, x % 7]);
+    /// }
+    /// for x in 0..90 {
+    ///     assert!(tree.remove(&[x, x % 7]));
+    ///     assert!(tree.remove(&[x, x % 7]));
+    ///     assert!(!tree.remove(&[x, x % 7]));
+    /// }
+    /// assert_eq!(tree.len(), 20);
+    /// assert_eq!(tree.nearest(&[0, 0]), Some(&[90, 6]));
+    /// ```
+    pub fn remove(&mut self, point: &P) -> bool {
+        let found = self.levels.iter().enumerate().find_map(|(level, points)| {
+            let found = points.as_ref()?.position(point)?;
+            Some((level, found))
+        });
+        let Some((level, index)) = found else {
+            return false;
+        };
+        let points = self.levels[level].as_mut().unwrap();
+        points.removed[index] = true;
+        points.len -= 1;
+        self.len -= 1;
+        self.removed += 1;
+        if points.len == 0 {
+            self.removed -= points.removed.len();
+            self.levels[level] = None;
+        }
+        if self.removed > self.len {
+            self.rebuild();
+        }
+        true
+    }
+
+    /// Drops removed points and splits the rest into the levels matching the
+    /// binary representation of their count.
+    fn rebuild(&mut self) {
+        let mut points: Vec<_> = self
+            .levels
+            .drain(..)
+            .flatten()
+            .flat_map(Level::into_points)
+            .collect();
+        let levels = usize::BITS - points.len().leading_zeros();
+        self.levels.resize_with(levels as usize, || None);
+        for (level, slot) in self.levels.iter_mut().enumerate().rev() {
+            if points.len() & (1 << level) != 0 {
+                *slot = Some(Level::new(points.split_off(points.len() - (1 << level))));
+            }
+        }
+        self.removed = 0;
+    }
+}
+
+impl<P> Level<P> {
+    fn new<T, const K: usize>(mut points: Vec<P>) -> Self
+    where
+        P: Point<T, K>,
+        T: Coordinate,
+    {
+        let mut nodes = vec![Node::default(); points.len()];
+        let root = build(&mut points, &KDTreeBuilder::new(), |node| {
+            nodes[node.index] = node
+        });
+        Self {
+            root,
+            removed: vec![false; points.len()],
+            len: points.len(),
+            points,
+            nodes,
+        }
+    }
+
+    fn view<T, const K: usize>(&self) -> View<'_, T, P, K>
+    where
+        P: Point<T, K>,
+        T: Coordinate,
+    {
+        View::explicit(&self.points, &self.nodes, self.root)
+    }
+
+    fn into_points(self) -> impl Iterator<Item = P> {
+        self.points
+            .into_iter()
+            .zip(self.removed)
+            .filter_map(|(point, removed)| (!removed).then_some(point))
+    }
+
+    fn position<T, const K: usize>(&self, point: &P) -> Option<usize>
+    where
+        P: Point<T, K> + PartialEq,
+        T: Coordinate,
+    {
+        self.view::<T, K>()
+            .position(point, |index| !self.removed[index])
+    }
+}
+
+/// Skips removed points of a level and shifts the rest past the levels below.
+struct Live<'a, C> {
+    candidates: &'a mut C,
+    removed: &'a [bool],
+    offset: usize,
+}
+
+impl<T, C: Candidates<T>> Candidates<T> for Live<'_, C> {
+    fn bound(&self) -> Option<T> {
+        self.candidates.bound()
+    }
+
+    fn offer(&mut self, index: usize, distance: T) {
+        if !self.removed[index] {
+            self.candidates.offer(self.offset + index, distance);
+        }
+    }
+}
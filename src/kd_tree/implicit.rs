@@ -0,0 +1,119 @@
+use std::marker::PhantomData;
+
+use crate::stack_vec::StackVec;
+
+use super::{
+    Coordinate, KDTreeBuilder, Metric, Point, Range, SquaredEuclidean, View, WithinRadius,
+    k_nearest, squared,
+};
+
+/// Static tree over `N` points that keeps no nodes at all.
+///
+/// The tree structure follows from the order of `points`: every range is
+/// split at its middle point on an axis cycling with depth, so the tree
+/// takes no more memory than the points themselves. Queries answer the same
+/// as on a [`KDTree`](super::KDTree) built with the same options.
+/// ```
+/// use kuina::kd_tree::{ImplicitKDTree, KDTree};
+/// let points = [[0, 0], [1, 9], [2, 2], [3, 7], [4, 4], [5, 5], [6, 1]];
+/// let implicit = ImplicitKDTree::<i32, _, 2, 7>::from(points);
+/// let explicit = KDTree::<i32, _, 2, 7>::from(points);
+/// assert_eq!(implicit.points(), explicit.points());
+/// assert_eq!(implicit.nearest(&[5, 6]), explicit.nearest(&[5, 6]));
+/// ```
+pub struct ImplicitKDTree<T, P, const K: usize, const N: usize>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    pub(super) points: [P; N],
+    pub(super) bucket_size: usize,
+    pub(super) _m: PhantomData<T>,
+}
+
+impl<T, P, const K: usize, const N: usize> From<[P; N]> for ImplicitKDTree<T, P, K, N>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    /// # Panics
+    /// If any coordinate is NaN.
+    fn from(value: [P; N]) -> Self {
+        KDTreeBuilder::new().build_implicit(value)
+    }
+}
+
+impl<T, P, const K: usize, const N: usize> ImplicitKDTree<T, P, K, N>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    pub fn points(&self) -> &[P] {
+        &self.points
+    }
+
+    fn view(&self) -> View<'_, T, P, K> {
+        View::implicit(&self.points, self.bucket_size)
+    }
+
+    /// Finds the point closest to `query` in squared euclidean distance.
+    pub fn nearest(&self, query: &P) -> Option<&P> {
+        self.nearest_by(query, &SquaredEuclidean)
+    }
+
+    /// Finds the point closest to `query` in the distance given by `metric`.
+    pub fn nearest_by<D: Metric<T, K>>(&self, query: &P, metric: &D) -> Option<&P> {
+        let mut best = None;
+        self.view().search(query, metric, &mut best);
+        best.map(|(index, _)| &self.points[index])
+    }
+
+    /// Finds the `M` points closest to `query` in squared euclidean distance,
+    /// sorted from the closest one, together with their distances.
+    pub fn k_nearest<const M: usize>(&self, query: &P) -> StackVec<(&P, T::Distance), M> {
+        self.k_nearest_by(query, &SquaredEuclidean)
+    }
+
+    /// Finds the `M` points closest to `query` in the distance given by
+    /// `metric`, sorted from the closest one, together with their distances.
+    pub fn k_nearest_by<const M: usize, D: Metric<T, K>>(
+        &self,
+        query: &P,
+        metric: &D,
+    ) -> StackVec<(&P, T::Distance), M> {
+        k_nearest(
+            |heap| self.view().search(query, metric, heap),
+            |index| &self.points[index],
+        )
+    }
+
+    /// Lazily yields every point inside the box spanned by `min` and `max`,
+    /// both corners included.
+    pub fn range<'a>(&'a self, min: &'a P, max: &'a P) -> Range<'a, T, P, K> {
+        self.view().range(min, max)
+    }
+
+    /// Lazily yields every point whose euclidean distance to `center` is at
+    /// most `radius`, squared in [`Coordinate::Distance`].
+    ///
+    /// # Panics
+    /// If `radius` is negative.
+    pub fn within_radius<'a>(
+        &'a self,
+        center: &'a P,
+        radius: T,
+    ) -> WithinRadius<'a, T, P, K, SquaredEuclidean> {
+        self.within_radius_by(center, squared(radius), &SquaredEuclidean)
+    }
+
+    /// Lazily yields every point whose distance to `center` given by `metric`
+    /// is at most `radius`, measured in the units of `metric`.
+    pub fn within_radius_by<'a, D: Metric<T, K>>(
+        &'a self,
+        center: &'a P,
+        radius: T::Distance,
+        metric: &'a D,
+    ) -> WithinRadius<'a, T, P, K, D> {
+        self.view().within_radius(center, radius, metric)
+    }
+}
@@ -0,0 +1,167 @@
+use super::{Coordinate, Point, at};
+
+/// Distance used by the nearest and radius queries of a
+/// [`KDTree`](super::KDTree), measured in the [`Coordinate::Distance`] type
+/// so that squares of integer coordinates do not overflow and sums of them
+/// saturate.
+///
+/// For pruning to stay correct `plane_distance` must never exceed the
+/// distance from a point to anything on the far side of a splitting plane,
+/// and `box_distance` the distance to anything inside a box.
+/// ```
+/// use kuina::kd_tree::{Coordinate, KDTree, Metric, Point};
+/// struct P2(i64, i64);
+/// impl Point<i64, 2> for P2 {
+///     fn get(&self, index: usize) -> i64 {
+///         [self.0, self.1][index]
+///     }
+/// }
+/// /// Only the horizontal distance matters.
+/// struct Horizontal;
+/// impl Metric<i64, 2> for Horizontal {
+///     fn distance<P: Point<i64, 2>>(&self, a: &P, b: &P) -> u128 {
+///         Coordinate::abs_diff(a.get(0), b.get(0))
+///     }
+///     fn plane_distance(&self, axis: usize, delta: u128) -> u128 {
+///         if axis == 0 { delta } else { 0 }
+///     }
+/// }
+/// let tree: KDTree<i64, P2, 2, 3> = [P2(0, 0), P2(5, 100), P2(9, 1)].into();
+/// assert_eq!(tree.nearest_by(&P2(6, 0), &Horizontal).map(|p| p.1), Some(100));
+/// ```
+pub trait Metric<T, const K: usize>
+where
+    T: Coordinate,
+{
+    fn distance<P: Point<T, K>>(&self, a: &P, b: &P) -> T::Distance;
+
+    /// Lower bound on the distance to a point `delta` away along `axis`.
+    fn plane_distance(&self, axis: usize, delta: T::Distance) -> T::Distance;
+
+    /// Lower bound on the distance to a point at least `gaps[axis]` away
+    /// along every axis. The largest plane distance is one for any metric.
+    /// ```
+    /// use kuina::kd_tree::{Chebyshev, Metric, SquaredEuclidean};
+    /// assert_eq!(Metric::<i32, 2>::box_distance(&SquaredEuclidean, [3, 4]), 25);
+    /// assert_eq!(Metric::<i32, 2>::box_distance(&Chebyshev, [3, 4]), 4);
+    /// ```
+    fn box_distance(&self, gaps: [T::Distance; K]) -> T::Distance {
+        (0..K).fold(T::Distance::ZERO, |max, axis| {
+            let distance = self.plane_distance(axis, gaps[axis]);
+            if distance > max { distance } else { max }
+        })
+    }
+}
+
+/// Sum of the plane distances of every gap, the box distance of metrics
+/// adding up one term per axis.
+fn sum_of_planes<T, D, const K: usize>(metric: &D, gaps: [T::Distance; K]) -> T::Distance
+where
+    D: Metric<T, K>,
+    T: Coordinate,
+{
+    (0..K).fold(T::Distance::ZERO, |sum, axis| {
+        sum.saturating_add(metric.plane_distance(axis, gaps[axis]))
+    })
+}
+
+/// Sum of squared differences along every axis, it orders points the same
+/// way as the euclidean distance.
+/// ```
+/// use kuina::kd_tree::{Metric, SquaredEuclidean};
+/// assert_eq!(SquaredEuclidean.distance(&[1_i32, 2], &[4, 6]), 25);
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SquaredEuclidean;
+
+impl<T: Coordinate, const K: usize> Metric<T, K> for SquaredEuclidean {
+    fn distance<P: Point<T, K>>(&self, a: &P, b: &P) -> T::Distance {
+        (0..K).fold(T::Distance::ZERO, |sum, axis| {
+            let delta = at(a, axis).abs_diff(at(b, axis));
+            sum.saturating_add(delta.saturating_mul(delta))
+        })
+    }
+
+    fn plane_distance(&self, _: usize, delta: T::Distance) -> T::Distance {
+        delta.saturating_mul(delta)
+    }
+
+    fn box_distance(&self, gaps: [T::Distance; K]) -> T::Distance {
+        sum_of_planes::<T, _, K>(self, gaps)
+    }
+}
+
+/// Sum of absolute differences along every axis.
+/// ```
+/// use kuina::kd_tree::{Manhattan, Metric};
+/// assert_eq!(Manhattan.distance(&[1_i32, 2], &[4, 6]), 7);
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Manhattan;
+
+impl<T: Coordinate, const K: usize> Metric<T, K> for Manhattan {
+    fn distance<P: Point<T, K>>(&self, a: &P, b: &P) -> T::Distance {
+        (0..K).fold(T::Distance::ZERO, |sum, axis| {
+            sum.saturating_add(at(a, axis).abs_diff(at(b, axis)))
+        })
+    }
+
+    fn plane_distance(&self, _: usize, delta: T::Distance) -> T::Distance {
+        delta
+    }
+
+    fn box_distance(&self, gaps: [T::Distance; K]) -> T::Distance {
+        sum_of_planes::<T, _, K>(self, gaps)
+    }
+}
+
+/// Largest absolute difference along any axis.
+/// ```
+/// use kuina::kd_tree::{Chebyshev, Metric};
+/// assert_eq!(Chebyshev.distance(&[1_i32, 2], &[4, 6]), 4);
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Chebyshev;
+
+impl<T: Coordinate, const K: usize> Metric<T, K> for Chebyshev {
+    fn distance<P: Point<T, K>>(&self, a: &P, b: &P) -> T::Distance {
+        (0..K).fold(T::Distance::ZERO, |max, axis| {
+            let delta = at(a, axis).abs_diff(at(b, axis));
+            if delta > max { delta } else { max }
+        })
+    }
+
+    fn plane_distance(&self, _: usize, delta: T::Distance) -> T::Distance {
+        delta
+    }
+}
+
+/// Squared euclidean distance with every axis scaled by its own
+/// non-negative weight.
+/// ```
+/// use kuina::kd_tree::{Metric, WeightedEuclidean};
+/// let metric = WeightedEuclidean { weights: [1, 10] };
+/// assert_eq!(metric.distance(&[1_i32, 2], &[4, 6]), 169);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct WeightedEuclidean<T, const K: usize> {
+    pub weights: [T; K],
+}
+
+impl<T: Coordinate, const K: usize> Metric<T, K> for WeightedEuclidean<T, K> {
+    fn distance<P: Point<T, K>>(&self, a: &P, b: &P) -> T::Distance {
+        (0..K).fold(T::Distance::ZERO, |sum, axis| {
+            let delta = at(a, axis).abs_diff(at(b, axis));
+            sum.saturating_add(self.plane_distance(axis, delta))
+        })
+    }
+
+    fn plane_distance(&self, axis: usize, delta: T::Distance) -> T::Distance {
+        let weight = self.weights[axis].to_distance();
+        weight.saturating_mul(delta).saturating_mul(delta)
+    }
+
+    fn box_distance(&self, gaps: [T::Distance; K]) -> T::Distance {
+        sum_of_planes::<T, _, K>(self, gaps)
+    }
+}
@@ -0,0 +1,161 @@
+use std::marker::PhantomData;
+
+use super::{
+    Coordinate, ImplicitKDTree, KDTree, KDTreeBuilder, Node, Point, assert_no_nan, build_subtree,
+    split_range,
+};
+
+/// Subtrees of at most this many points are built on the current thread.
+const PARALLEL_THRESHOLD: usize = 1 << 12;
+
+impl KDTreeBuilder {
+    /// Builds the same tree as [`build`](Self::build), splitting subtrees
+    /// larger than a few thousand points between rayon threads. Trees that
+    /// large can overflow the stack when built by value, see [`KDTree`], use
+    /// [`par_build_boxed`](Self::par_build_boxed) for them.
+    /// ```
+    /// use kuina::kd_tree::{KDTree, KDTreeBuilder};
+    /// let points = [[0, 0], [1, 9], [2, 2], [3, 7], [4, 4], [5, 5], [6, 1]];
+    /// let tree = KDTreeBuilder::new().par_build::<i32, _, 2, 7>(points);
+    /// assert_eq!(tree.points(), KDTree::<i32, _, 2, 7>::from(points).points());
+    /// ```
+    /// # Panics
+    /// If any coordinate is NaN.
+    pub fn par_build<T, P, const K: usize, const N: usize>(
+        &self,
+        points: [P; N],
+    ) -> KDTree<T, P, K, N>
+    where
+        P: Point<T, K> + Send,
+        T: Coordinate,
+    {
+        assert_no_nan(&points);
+        KDTree::from_indexed(points, self, |points, nodes| {
+            par_build(points, Some(nodes), self)
+        })
+    }
+
+    /// Builds the same tree as [`build_boxed`](Self::build_boxed), splitting
+    /// subtrees larger than a few thousand points between rayon threads.
+    /// # Panics
+    /// If any coordinate is NaN.
+    pub fn par_build_boxed<T, P, const K: usize, const N: usize>(
+        &self,
+        points: Box<[P; N]>,
+    ) -> Box<KDTree<T, P, K, N>>
+    where
+        P: Point<T, K> + Send,
+        T: Coordinate,
+    {
+        assert_no_nan(&points[..]);
+        KDTree::from_indexed_boxed(points, self, |points, nodes| {
+            par_build(points, Some(nodes), self)
+        })
+    }
+
+    /// Builds the same tree as [`build_implicit`](Self::build_implicit),
+    /// splitting subtrees larger than a few thousand points between rayon
+    /// threads.
+    /// # Panics
+    /// If any coordinate is NaN or the split is not [`Split::Cycle`](super::Split::Cycle).
+    pub fn par_build_implicit<T, P, const K: usize, const N: usize>(
+        &self,
+        points: [P; N],
+    ) -> ImplicitKDTree<T, P, K, N>
+    where
+        P: Point<T, K> + Send,
+        T: Coordinate,
+    {
+        self.assert_implicit(&points);
+        let mut points = points;
+        par_build(&mut points, None, self);
+        ImplicitKDTree {
+            points,
+            bucket_size: self.bucket_size,
+            _m: PhantomData,
+        }
+    }
+}
+
+/// Same as `build`, with `nodes` left out for implicit trees.
+fn par_build<T, P, const K: usize>(
+    points: &mut [P],
+    nodes: Option<&mut [Node]>,
+    options: &KDTreeBuilder,
+) -> Option<usize>
+where
+    P: Point<T, K> + Send,
+    T: Coordinate,
+{
+    const { assert!(K > 0, "points need at least one dimension") };
+    (!points.is_empty()).then(|| par_build_subtree(points, nodes, 0, 0, 0, options))
+}
+
+/// Same as `build_subtree`, builds both halves below the root in parallel
+/// while the subtree is large. `nodes` covers the same range as `points`.
+fn par_build_subtree<T, P, const K: usize>(
+    points: &mut [P],
+    nodes: Option<&mut [Node]>,
+    bound_left: usize,
+    axis: usize,
+    depth: usize,
+    options: &KDTreeBuilder,
+) -> usize
+where
+    P: Point<T, K> + Send,
+    T: Coordinate,
+{
+    if points.len() <= PARALLEL_THRESHOLD.max(options.bucket_size) {
+        return match nodes {
+            Some(nodes) => build_subtree(points, bound_left, axis, depth, options, |node| {
+                nodes[node.index - bound_left] = node
+            }),
+            None => build_subtree(points, bound_left, axis, depth, options, |_| {}),
+        };
+    }
+    let mut root = split_range(points, bound_left, axis, depth, options);
+    let middle = root.index - bound_left;
+    let (left_points, rest) = points.split_at_mut(middle);
+    let right_points = &mut rest[1..];
+    let (left_nodes, root_node, right_nodes) = match nodes {
+        Some(nodes) => {
+            let (left, rest) = nodes.split_at_mut(middle);
+            let (root, right) = rest.split_first_mut().unwrap();
+            (Some(left), Some(root), Some(right))
+        }
+        None => (None, None, None),
+    };
+    let axis = (root.axis + 1) % K;
+    let (left, right) = rayon::join(
+        || {
+            (!left_points.is_empty()).then(|| {
+                par_build_subtree(
+                    left_points,
+                    left_nodes,
+                    bound_left,
+                    axis,
+                    depth + 1,
+                    options,
+                )
+            })
+        },
+        || {
+            (!right_points.is_empty()).then(|| {
+                par_build_subtree(
+                    right_points,
+                    right_nodes,
+                    root.index + 1,
+                    axis,
+                    depth + 1,
+                    options,
+                )
+            })
+        },
+    );
+    root.left = left;
+    root.right = right;
+    if let Some(node) = root_node {
+        *node = root;
+    }
+    root.index
+}
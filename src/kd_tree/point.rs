@@ -0,0 +1,76 @@
+use super::{Coordinate, Point};
+
+/// ```
+/// use kuina::kd_tree::KDTree;
+/// let tree = KDTree::<f32, _, 3, 3>::from([[0.0, 1.0, 2.0], [3.0, 4.0, 5.0], [6.0, 7.0, 8.0]]);
+/// assert_eq!(tree.nearest(&[4.0, 4.0, 4.0]), Some(&[3.0, 4.0, 5.0]));
+/// ```
+impl<T: Coordinate, const K: usize> Point<T, K> for [T; K] {
+    fn get(&self, index: usize) -> T {
+        self[index]
+    }
+}
+
+/// ```
+/// use kuina::kd_tree::KDTree;
+/// let tree = KDTree::<i32, _, 2, 3>::from([(0, 0), (5, 5), (9, 1)]);
+/// assert_eq!(tree.nearest(&(8, 3)), Some(&(9, 1)));
+/// ```
+impl<T: Coordinate> Point<T, 2> for (T, T) {
+    fn get(&self, index: usize) -> T {
+        [self.0, self.1][index]
+    }
+}
+
+/// ```
+/// use kuina::kd_tree::KDTree;
+/// let tree = KDTree::<u8, _, 3, 2>::from([(0, 0, 0), (5, 5, 5)]);
+/// assert_eq!(tree.nearest(&(4, 4, 1)), Some(&(5, 5, 5)));
+/// ```
+impl<T: Coordinate> Point<T, 3> for (T, T, T) {
+    fn get(&self, index: usize) -> T {
+        [self.0, self.1, self.2][index]
+    }
+}
+
+/// Lets a tree index points owned somewhere else.
+/// ```
+/// use kuina::kd_tree::KDTree;
+/// let points = [[0, 0], [5, 5], [9, 1]];
+/// let tree = KDTree::<i32, _, 2, 3>::from([&points[0], &points[1], &points[2]]);
+/// assert_eq!(tree.nearest(&&[8, 3]), Some(&&[9, 1]));
+/// ```
+impl<T: Coordinate, P: Point<T, K>, const K: usize> Point<T, K> for &P {
+    fn get(&self, index: usize) -> T {
+        (**self).get(index)
+    }
+}
+
+/// Implements [`Point`] for a struct, its named fields become the axes in
+/// the order they are listed.
+/// ```
+/// use kuina::{impl_point, kd_tree::KDTree};
+/// #[derive(Debug, PartialEq)]
+/// struct Vec3 {
+///     x: f64,
+///     y: f64,
+///     z: f64,
+/// }
+/// impl_point!(Vec3 { x, y, z }: f64);
+/// let tree = KDTree::<f64, _, 3, 2>::from([
+///     Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+///     Vec3 { x: 1.0, y: 1.0, z: 1.0 },
+/// ]);
+/// let query = Vec3 { x: 0.9, y: 0.2, z: 0.8 };
+/// assert_eq!(tree.nearest(&query), Some(&Vec3 { x: 1.0, y: 1.0, z: 1.0 }));
+/// ```
+#[macro_export]
+macro_rules! impl_point {
+    ($point:ty { $($field:ident),+ $(,)? }: $t:ty) => {
+        impl $crate::kd_tree::Point<$t, { [$(stringify!($field)),+].len() }> for $point {
+            fn get(&self, index: usize) -> $t {
+                [$(self.$field),+][index]
+            }
+        }
+    };
+}
@@ -0,0 +1,208 @@
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    marker::PhantomData,
+};
+
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, IgnoredAny, SeqAccess, Visitor},
+    ser::SerializeStruct,
+};
+
+use super::{Bounds, Coordinate, KDTree, MAX_DEPTH, Node, Point, node_bounds};
+
+/// Stores the permuted points and the nodes, so deserializing never has to
+/// rebuild the tree. Tight node boxes are recomputed on load.
+impl<T, P, const K: usize, const N: usize> Serialize for KDTree<T, P, K, N>
+where
+    P: Point<T, K> + Serialize,
+    T: Coordinate,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("KDTree", 5)?;
+        state.serialize_field("points", &self.points[..])?;
+        state.serialize_field("indices", &self.indices[..])?;
+        state.serialize_field("nodes", &self.nodes[..])?;
+        state.serialize_field("root", &self.root)?;
+        state.serialize_field("tight_bounds", &self.boxes.is_some())?;
+        state.end()
+    }
+}
+
+/// Takes the stored tree as is after checking that queries can walk it.
+impl<'de, T, P, const K: usize, const N: usize> Deserialize<'de> for KDTree<T, P, K, N>
+where
+    P: Point<T, K> + Deserialize<'de>,
+    T: Coordinate,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let stored = Stored::<P, N>::deserialize(deserializer)?;
+        let mut tree = KDTree {
+            bounds: Bounds::of(&stored.points.0),
+            points: stored.points.0,
+            indices: stored.indices.0,
+            nodes: stored.nodes.0,
+            root: stored.root,
+            boxes: None,
+        };
+        if !tree.is_valid() {
+            return Err(de::Error::custom(
+                "nodes do not form a KDTree over the points",
+            ));
+        }
+        if stored.tight_bounds {
+            tree.boxes = Some(node_bounds(&tree.points, &tree.nodes, tree.root));
+        }
+        Ok(tree)
+    }
+}
+
+impl<T, P, const K: usize, const N: usize> KDTree<T, P, K, N>
+where
+    P: Point<T, K> + Serialize,
+    T: Coordinate,
+{
+    /// Writes the built tree in a compact binary form, read it back with
+    /// [`load`](Self::load).
+    /// ```
+    /// use kuina::kd_tree::KDTree;
+    /// let tree = KDTree::<i32, [i32; 2], 2, 3>::from([[0, 0], [10, 0], [3, 4]]);
+    /// let mut bytes = Vec::new();
+    /// tree.dump(&mut bytes).unwrap();
+    /// let loaded = KDTree::<i32, [i32; 2], 2, 3>::load(&bytes[..]).unwrap();
+    /// assert_eq!(loaded.nearest(&[4, 4]), Some(&[3, 4]));
+    /// assert!(KDTree::<i32, [i32; 2], 2, 2>::load(&bytes[..]).is_err());
+    /// ```
+    pub fn dump(&self, mut writer: impl Write) -> io::Result<()> {
+        bincode::serde::encode_into_std_write(self, &mut writer, bincode::config::standard())
+            .map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+impl<T, P, const K: usize, const N: usize> KDTree<T, P, K, N>
+where
+    P: Point<T, K> + for<'de> Deserialize<'de>,
+    T: Coordinate,
+{
+    /// Reads a tree written by [`dump`](Self::dump) without rebuilding it.
+    pub fn load(mut reader: impl Read) -> io::Result<Self> {
+        bincode::serde::decode_from_std_read(&mut reader, bincode::config::standard())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+impl<T, P, const K: usize, const N: usize> KDTree<T, P, K, N>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    /// Whether `indices` is a permutation and every node reachable from the
+    /// root owns its point, splits on an existing axis and has children
+    /// covering exactly the two halves of its range, within the depth the
+    /// query stacks hold.
+    fn is_valid(&self) -> bool {
+        let mut seen = vec![false; N];
+        for &index in &self.indices {
+            match seen.get_mut(index) {
+                Some(seen) if !*seen => *seen = true,
+                _ => return false,
+            }
+        }
+        let Some(root) = self.root else {
+            return N == 0;
+        };
+        let mut stack = vec![(root, 0, N, 1)];
+        while let Some((id, bound_left, bound_right, depth)) = stack.pop() {
+            let Some(node) = self.nodes.get(id) else {
+                return false;
+            };
+            if node.index != id
+                || node.bound_left != bound_left
+                || node.bound_right != bound_right
+                || !(bound_left..bound_right).contains(&id)
+                || node.axis >= K
+                || depth > MAX_DEPTH
+            {
+                return false;
+            }
+            if node.is_leaf() {
+                continue;
+            }
+            match node.left {
+                Some(left) => stack.push((left, bound_left, id, depth + 1)),
+                None if id != bound_left => return false,
+                None => {}
+            }
+            match node.right {
+                Some(right) => stack.push((right, id + 1, bound_right, depth + 1)),
+                None if id + 1 != bound_right => return false,
+                None => {}
+            }
+        }
+        true
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "KDTree")]
+struct Stored<P, const N: usize> {
+    points: Array<P, N>,
+    indices: Array<usize, N>,
+    nodes: Array<Node, N>,
+    root: Option<usize>,
+    tight_bounds: bool,
+}
+
+/// Array of any length read from a sequence of exactly `N` elements.
+struct Array<E, const N: usize>([E; N]);
+
+impl<'de, E, const N: usize> Deserialize<'de> for Array<E, N>
+where
+    E: Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(ArrayVisitor(PhantomData))
+    }
+}
+
+struct ArrayVisitor<E, const N: usize>(PhantomData<E>);
+
+impl<'de, E, const N: usize> Visitor<'de> for ArrayVisitor<E, N>
+where
+    E: Deserialize<'de>,
+{
+    type Value = Array<E, N>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a sequence of {N} elements")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut error = None;
+        let elements: [Option<E>; N] = std::array::from_fn(|index| {
+            if error.is_some() {
+                return None;
+            }
+            match seq.next_element() {
+                Ok(Some(element)) => Some(element),
+                Ok(None) => {
+                    error = Some(de::Error::invalid_length(index, &self));
+                    None
+                }
+                Err(e) => {
+                    error = Some(e);
+                    None
+                }
+            }
+        });
+        if let Some(error) = error {
+            return Err(error);
+        }
+        if seq.next_element::<IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(N + 1, &self));
+        }
+        Ok(Array(elements.map(Option::unwrap)))
+    }
+}
@@ -0,0 +1,193 @@
+use super::{Bounds, Coordinate, KDTreeBuilder, MAX_DEPTH, Node, Point, at};
+
+/// How a [`KDTreeBuilder`] picks the axis and the point every node splits
+/// at. Queries follow whatever axis each node ended up with.
+/// ```
+/// use kuina::kd_tree::{KDTree, KDTreeBuilder, Split};
+/// let points = [[0, 0], [1, 90], [2, 20], [3, 70], [4, 40], [5, 50], [6, 10]];
+/// let tree: KDTree<i32, _, 2, 7> = KDTreeBuilder::new().split(Split::WidestSpread).build(points);
+/// assert_eq!(tree.pre_order().next().map(|(_, info)| info.axis), Some(1));
+/// assert_eq!(tree.nearest(&[3, 60]), Some(&[3, 70]));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Split {
+    /// Median on an axis cycling with depth, the only layout implicit trees
+    /// can follow.
+    #[default]
+    Cycle,
+    /// Median on the axis the points spread the most along.
+    WidestSpread,
+    /// Middle of the widest side of the bounding box, slid to the closest
+    /// point when it would leave one side empty.
+    SlidingMidpoint,
+    /// Out of a few quantiles on every axis, the one minimizing the number
+    /// of points times the summed box extents on both sides, the closest to
+    /// the median among equal ones.
+    SurfaceArea,
+}
+
+/// Quantiles tried on every axis by [`Split::SurfaceArea`].
+const CANDIDATES: usize = 15;
+
+/// Picks how the non-empty `points`, starting at position `bound_left` and
+/// lying `depth` levels deep, get split and moves them accordingly: smaller
+/// ones before the split point, larger ones after. `axis` is the one a
+/// cycling split would take.
+///
+/// Splits other than medians may be lopsided, so once a subtree could grow
+/// past `MAX_DEPTH` with them it falls back to cycling medians, which halve
+/// the points with every level.
+pub(super) fn split_range<T, P, const K: usize>(
+    points: &mut [P],
+    bound_left: usize,
+    axis: usize,
+    depth: usize,
+    options: &KDTreeBuilder,
+) -> Node
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    let len = points.len();
+    let mut node = Node {
+        index: bound_left,
+        bound_left,
+        bound_right: bound_left + len,
+        axis,
+        ..Default::default()
+    };
+    if len <= options.bucket_size {
+        return node;
+    }
+    let bits = (usize::BITS - len.leading_zeros()) as usize;
+    let split = if depth + bits < MAX_DEPTH {
+        options.split
+    } else {
+        Split::Cycle
+    };
+    let (axis, position) = match split {
+        Split::Cycle => median(points, axis),
+        Split::WidestSpread => median(points, widest(points).0),
+        Split::SlidingMidpoint => sliding_midpoint(points),
+        Split::SurfaceArea => surface_area(points, axis),
+    };
+    node.axis = axis;
+    node.index = bound_left + position;
+    node
+}
+
+fn median<T, P, const K: usize>(points: &mut [P], axis: usize) -> (usize, usize)
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    let position = points.len() / 2;
+    points.select_nth_unstable_by(position, |a, b| at(a, axis).total_cmp(&at(b, axis)));
+    (axis, position)
+}
+
+/// Axis with the largest spread of coordinates, with its smallest and
+/// largest coordinate.
+fn widest<T, P, const K: usize>(points: &[P]) -> (usize, T, T)
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    let mut widest = (0, T::ZERO, T::ZERO);
+    for axis in 0..K {
+        let coordinates = points.iter().map(|point| at(point, axis));
+        let min = coordinates.clone().min_by(T::total_cmp).unwrap();
+        let max = coordinates.max_by(T::total_cmp).unwrap();
+        if axis == 0 || max.abs_diff(min) > widest.2.abs_diff(widest.1) {
+            widest = (axis, min, max);
+        }
+    }
+    widest
+}
+
+fn sliding_midpoint<T, P, const K: usize>(points: &mut [P]) -> (usize, usize)
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    let (axis, min, max) = widest(points);
+    if min == max {
+        return median(points, axis);
+    }
+    let mut below = 0;
+    for index in 0..points.len() {
+        let value = at(&points[index], axis);
+        if min.abs_diff(value) < value.abs_diff(max) {
+            points.swap(index, below);
+            below += 1;
+        }
+    }
+    let cmp = |a: &P, b: &P| at(a, axis).total_cmp(&at(b, axis));
+    if below == points.len() {
+        points.select_nth_unstable_by(below - 1, cmp);
+        return (axis, below - 1);
+    }
+    points[below..].select_nth_unstable_by(0, cmp);
+    (axis, below)
+}
+
+/// Median on `axis` unless some quantile costs less, which never happens
+/// while extents are NaN.
+fn surface_area<T, P, const K: usize>(points: &mut [P], axis: usize) -> (usize, usize)
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    let len = points.len();
+    let positions: [usize; CANDIDATES] = std::array::from_fn(|i| len * (i + 1) / (CANDIDATES + 1));
+    let mut best = (f64::INFINITY, axis, len / 2);
+    for axis in 0..K {
+        points.sort_unstable_by(|a, b| at(a, axis).total_cmp(&at(b, axis)));
+        let mut left = [0.0; CANDIDATES];
+        let mut bounds = None;
+        let mut candidate = 0;
+        for (index, point) in points.iter().enumerate() {
+            while candidate < CANDIDATES && positions[candidate] == index {
+                left[candidate] = extent(&bounds);
+                candidate += 1;
+            }
+            grow(&mut bounds, point);
+        }
+        let mut bounds = None;
+        let mut candidate = CANDIDATES;
+        for (index, point) in points.iter().enumerate().rev() {
+            while candidate > 0 && positions[candidate - 1] == index {
+                candidate -= 1;
+                let position = positions[candidate];
+                let cost = position as f64 * left[candidate]
+                    + (len - position - 1) as f64 * extent(&bounds);
+                let offset = position.abs_diff(len / 2);
+                if cost < best.0 || (cost == best.0 && offset < best.2.abs_diff(len / 2)) {
+                    best = (cost, axis, position);
+                }
+            }
+            grow(&mut bounds, point);
+        }
+    }
+    let (_, axis, position) = best;
+    if axis != K - 1 {
+        points.sort_unstable_by(|a, b| at(a, axis).total_cmp(&at(b, axis)));
+    }
+    (axis, position)
+}
+
+/// Grows `bounds` to hold `point`, starting from no box at all.
+fn grow<T, P, const K: usize>(bounds: &mut Option<Bounds<T, K>>, point: &P)
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    match bounds {
+        Some(bounds) => bounds.add(point),
+        None => *bounds = Some(Bounds::point(point)),
+    }
+}
+
+fn extent<T: Coordinate, const K: usize>(bounds: &Option<Bounds<T, K>>) -> f64 {
+    bounds.as_ref().map_or(0.0, Bounds::extent)
+}
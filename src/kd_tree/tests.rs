@@ -0,0 +1,826 @@
+use super::{
+    Chebyshev, Coordinate, DynamicKDTree, ImplicitKDTree, KDTree, KDTreeBuilder, Manhattan, Metric,
+    Point, Split, SquaredEuclidean, WeightedEuclidean,
+};
+
+/// Deterministic stream of coordinates for the randomized checks.
+struct Lcg(u64);
+
+impl Lcg {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Integer in `-500..500`.
+    fn next(&mut self) -> i64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) as i64 % 1000 - 500
+    }
+
+    /// Float in `0.0..1.0`.
+    fn unit(&mut self) -> f64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    fn point<const K: usize>(&mut self) -> [i64; K] {
+        std::array::from_fn(|_| self.next())
+    }
+
+    fn points<const K: usize, const N: usize>(&mut self) -> [[i64; K]; N] {
+        std::array::from_fn(|_| self.point())
+    }
+}
+
+/// Distances given by `metric` from `query` to every point, closest first.
+fn distances<T, P, D, const K: usize>(points: &[P], query: &P, metric: &D) -> Vec<T::Distance>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+    D: Metric<T, K>,
+{
+    let mut distances: Vec<_> = points.iter().map(|p| metric.distance(p, query)).collect();
+    distances.sort_by(T::Distance::total_cmp);
+    distances
+}
+
+/// Number of points at most `radius` away from `center` given by `metric`.
+fn count_within<T, P, D, const K: usize>(
+    points: &[P],
+    center: &P,
+    radius: T::Distance,
+    metric: &D,
+) -> usize
+where
+    P: Point<T, K>,
+    T: Coordinate,
+    D: Metric<T, K>,
+{
+    points
+        .iter()
+        .filter(|p| metric.distance(*p, center) <= radius)
+        .count()
+}
+
+/// Number of points inside the box spanned by `min` and `max`.
+fn count_inside<T, P, const K: usize>(points: &[P], min: &P, max: &P) -> usize
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    points
+        .iter()
+        .filter(|p| (0..K).all(|axis| min.get(axis) <= p.get(axis) && p.get(axis) <= max.get(axis)))
+        .count()
+}
+
+/// Every way of building a tree worth checking.
+fn builders() -> [KDTreeBuilder; 8] {
+    [
+        KDTreeBuilder::new(),
+        KDTreeBuilder::new().bucket_size(8),
+        KDTreeBuilder::new().tight_bounds(true),
+        KDTreeBuilder::new().bucket_size(6).tight_bounds(true),
+        KDTreeBuilder::new().split(Split::WidestSpread),
+        KDTreeBuilder::new().split(Split::SlidingMidpoint),
+        KDTreeBuilder::new().split(Split::SurfaceArea),
+        KDTreeBuilder::new()
+            .split(Split::SurfaceArea)
+            .bucket_size(4)
+            .tight_bounds(true),
+    ]
+}
+
+/// Checks the nearest, radius and range queries of `tree` with `metric`
+/// against scans over its points.
+fn check_queries<D, const N: usize>(tree: &KDTree<i64, [i64; 3], 3, N>, metric: &D, rng: &mut Lcg)
+where
+    D: Metric<i64, 3>,
+{
+    let points = tree.points();
+    for _ in 0..50 {
+        let query = rng.point();
+        let brute = distances(points, &query, metric);
+        let nearest = tree.nearest_by(&query, metric);
+        assert_eq!(
+            nearest.map(|p| metric.distance(p, &query)),
+            brute.first().copied()
+        );
+        let found = tree.k_nearest_by::<10, _>(&query, metric);
+        assert!(
+            found
+                .iter()
+                .map(|&(_, d)| d)
+                .eq(brute.iter().copied().take(10))
+        );
+        for &(point, d) in found.iter() {
+            assert_eq!(metric.distance(point, &query), d);
+        }
+        let radius = brute[rng.next().unsigned_abs() as usize % brute.len()];
+        let found = tree.within_radius_by(&query, radius, metric);
+        let found = found.inspect(|p| assert!(metric.distance(*p, &query) <= radius));
+        assert_eq!(found.count(), count_within(points, &query, radius, metric));
+        let max = query.map(|x| x + rng.next().abs() / 2);
+        let found = tree.range(&query, &max);
+        assert_eq!(found.count(), count_inside(points, &query, &max));
+    }
+}
+
+#[test]
+fn queries_match_brute_force() {
+    let mut rng = Lcg::new(1);
+    let points: [[i64; 3]; 300] = rng.points();
+    for builder in builders() {
+        let tree = builder.build::<i64, _, 3, 300>(points);
+        check_queries(&tree, &SquaredEuclidean, &mut rng);
+        check_queries(&tree, &Manhattan, &mut rng);
+        check_queries(&tree, &Chebyshev, &mut rng);
+        check_queries(&tree, &WeightedEuclidean { weights: [1, 4, 9] }, &mut rng);
+    }
+}
+
+#[test]
+fn radius_is_euclidean() {
+    let mut rng = Lcg::new(2);
+    let tree = KDTree::<i64, [i64; 2], 2, 300>::from(rng.points());
+    for _ in 0..100 {
+        let (center, radius) = (rng.point(), rng.next().abs() / 2);
+        let inside = count_within(
+            tree.points(),
+            &center,
+            u128::from(radius.unsigned_abs()).pow(2),
+            &SquaredEuclidean,
+        );
+        assert_eq!(tree.within_radius(&center, radius).count(), inside);
+    }
+}
+
+#[test]
+fn nearest_in_high_dimensions() {
+    let mut rng = Lcg::new(3);
+    let mut point = || std::array::from_fn(|_| rng.unit() as f32);
+    let points: [[f32; 8]; 200] = std::array::from_fn(|_| point());
+    let tree = KDTree::<f32, _, 8, 200>::from(points);
+    for _ in 0..50 {
+        let query = point();
+        let brute = distances(&points, &query, &SquaredEuclidean);
+        let found = tree
+            .nearest(&query)
+            .map(|p| SquaredEuclidean.distance(p, &query));
+        assert_eq!(found, Some(brute[0]));
+    }
+}
+
+#[test]
+fn distances_do_not_overflow_narrow_coordinates() {
+    let mut rng = Lcg::new(18);
+    let wide: [[i32; 2]; 100] = std::array::from_fn(|_| rng.point().map(|x| x as i32 * 140));
+    let tree = KDTree::<i32, _, 2, 100>::from(wide);
+    for query in [[-70_000, 70_000], [70_000, -70_000], [0, 0]] {
+        let brute = distances(&wide, &query, &SquaredEuclidean);
+        let found = tree.k_nearest::<100>(&query);
+        assert!(found.iter().map(|&(_, d)| d).eq(brute.iter().copied()));
+        let brute = distances(&wide, &query, &Manhattan);
+        let found = tree.nearest_by(&query, &Manhattan);
+        assert_eq!(found.map(|p| Manhattan.distance(p, &query)), Some(brute[0]));
+    }
+    assert_eq!(
+        SquaredEuclidean.distance(&[-70_000_i32, 0], &[70_000, 0]),
+        19_600_000_000
+    );
+
+    let bytes: [[u8; 3]; 100] = std::array::from_fn(|_| rng.point().map(|x| (x + 500) as u8));
+    let tree = KDTree::<u8, _, 3, 100>::from(bytes);
+    for query in [[0, 0, 0], [255, 255, 255], [0, 255, 128]] {
+        let brute = distances(&bytes, &query, &SquaredEuclidean);
+        let found = tree.k_nearest::<100>(&query);
+        assert!(found.iter().map(|&(_, d)| d).eq(brute.iter().copied()));
+        let weighted = WeightedEuclidean {
+            weights: [1, 200, 3],
+        };
+        let brute = distances(&bytes, &query, &weighted);
+        let found = tree.nearest_by(&query, &weighted);
+        assert_eq!(found.map(|p| weighted.distance(p, &query)), Some(brute[0]));
+    }
+}
+
+#[test]
+fn distances_hold_at_integer_extremes() {
+    macro_rules! check {
+        ($($t:ty => $d:ty)*) => {$({
+            let (min, max) = (<$t>::MIN, <$t>::MAX);
+            let spread = max.abs_diff(min) as $d;
+            let square = spread.saturating_mul(spread);
+            assert_eq!(Coordinate::abs_diff(min, max), spread);
+            let line = KDTree::<$t, [$t; 1], 1, 2>::from([[min], [max]]);
+            let found = line.k_nearest::<2>(&[min]);
+            assert_eq!(found[..], [(&[min], 0), (&[max], square)]);
+            let plane = KDTree::<$t, [$t; 2], 2, 2>::from([[min, min], [max, max]]);
+            let found = plane.k_nearest::<2>(&[max, max]);
+            assert_eq!(found[..], [(&[max, max], 0), (&[min, min], square.saturating_add(square))]);
+            assert_eq!(plane.nearest(&[min + 1, min]), Some(&[min, min]));
+            let found = plane.nearest_by(&[max, min], &Manhattan);
+            assert_eq!(found.map(|p| Manhattan.distance(p, &[max, min])), Some(spread));
+            assert_eq!(Chebyshev.distance(&[min, max], &[max, min]), spread);
+        })*};
+    }
+    check! {
+        i8 => u32 i16 => u64 i32 => u64 i64 => u128 i128 => u128 isize => u128
+        u8 => u32 u16 => u64 u32 => u64 u64 => u128 u128 => u128 usize => u128
+    }
+}
+
+#[test]
+fn radii_are_squared_in_the_distance_type() {
+    let mut rng = Lcg::new(19);
+    let points: [[i32; 2]; 100] = std::array::from_fn(|_| rng.point().map(|x| x as i32 * 140));
+    let tree = KDTree::<i32, _, 2, 100>::from(points);
+    let implicit = ImplicitKDTree::<i32, _, 2, 100>::from(points);
+    for radius in [50_000_i32, 70_000, 100_000] {
+        let squared = u64::from(radius.unsigned_abs()).pow(2);
+        for center in [[-70_000, 70_000], [0, 0]] {
+            let inside = count_within(&points, &center, squared, &SquaredEuclidean);
+            assert_eq!(tree.within_radius(&center, radius).count(), inside);
+            assert_eq!(implicit.within_radius(&center, radius).count(), inside);
+        }
+        let pairs = (0..100).flat_map(|i| (0..100).map(move |j| (i, j)));
+        let apart: Vec<_> = pairs
+            .map(|(i, j)| SquaredEuclidean.distance(&points[i], &points[j]))
+            .collect();
+        let brute = apart.iter().filter(|&&d| d <= squared).count();
+        assert_eq!(tree.join_within(&tree, radius).count(), brute);
+        let closer = apart.iter().filter(|&&d| d < squared).count();
+        assert_eq!(tree.pairs_within(radius).count(), (closer - 100) / 2);
+    }
+
+    let bytes = KDTree::<u8, [u8; 1], 1, 2>::from([[0], [255]]);
+    assert_eq!(bytes.within_radius(&[0], 255).count(), 2);
+    assert_eq!(bytes.join_within(&bytes, 255).count(), 4);
+    assert_eq!(bytes.pairs_within(255).count(), 0);
+    let bytes = KDTree::<u8, [u8; 1], 1, 2>::from([[0], [254]]);
+    assert_eq!(bytes.pairs_within(255).count(), 1);
+}
+
+#[test]
+fn approximate_nearest_stays_within_factor() {
+    let mut rng = Lcg::new(4);
+    let mut point = || std::array::from_fn(|_| rng.unit());
+    let tree = KDTree::<f64, [f64; 6], 6, 500>::from(std::array::from_fn(|_| point()));
+    let distance =
+        |p: Option<&[f64; 6]>, query: &[f64; 6]| SquaredEuclidean.distance(p.unwrap(), query);
+    for _ in 0..50 {
+        let query = point();
+        let exact = distance(tree.nearest(&query), &query);
+        let approx = distance(tree.nearest_approx(&query, 0.5), &query);
+        assert!(exact <= approx && approx <= 1.5 * exact);
+        assert_eq!(distance(tree.nearest_approx(&query, 0.0), &query), exact);
+    }
+
+    let tree = KDTree::<i64, [i64; 3], 3, 500>::from(rng.points());
+    for _ in 0..50 {
+        let query = rng.point();
+        let exact = SquaredEuclidean.distance(tree.nearest(&query).unwrap(), &query);
+        let approx = SquaredEuclidean.distance(tree.nearest_approx(&query, 0.5).unwrap(), &query);
+        assert!(exact <= approx && approx as f64 <= 1.5 * exact as f64);
+    }
+}
+
+#[test]
+fn nearest_where_matches_filtered_scan() {
+    let mut rng = Lcg::new(5);
+    let points: [[i64; 3]; 300] = std::array::from_fn(|_| [rng.next(), rng.next(), rng.next() % 4]);
+    let tree = KDTree::<i64, _, 3, 300>::from(points);
+    let metric = SquaredEuclidean;
+    for query in tree.points() {
+        let others: Vec<_> = points.iter().filter(|&p| p != query).copied().collect();
+        let found = tree.nearest_where(query, |p| p != query);
+        let brute = distances(&others, query, &metric);
+        assert_eq!(
+            found.map(|p| metric.distance(p, query)),
+            brute.first().copied()
+        );
+
+        let category: Vec<_> = points.iter().filter(|p| p[2] == 1).copied().collect();
+        let found = tree.nearest_where(query, |p| p[2] == 1);
+        assert!(found.is_none_or(|p| p[2] == 1));
+        let brute = distances(&category, query, &metric);
+        assert_eq!(
+            found.map(|p| metric.distance(p, query)),
+            brute.first().copied()
+        );
+    }
+}
+
+#[test]
+fn indexed_queries_return_input_positions() {
+    let mut rng = Lcg::new(6);
+    let input: [[i64; 2]; 300] = rng.points();
+    let tree = KDTree::<i64, _, 2, 300>::from(input);
+    for _ in 0..100 {
+        let query = rng.point();
+        let brute = distances(&input, &query, &SquaredEuclidean);
+        let (index, found) = tree.nearest_indexed(&query).unwrap();
+        assert_eq!(
+            (SquaredEuclidean.distance(&input[index], &query), found),
+            (brute[0], brute[0])
+        );
+        let found = tree.k_nearest_indexed::<5>(&query);
+        assert!(found.iter().map(|&(_, d)| d).eq(brute[..5].iter().copied()));
+        for (index, found) in found {
+            assert_eq!(SquaredEuclidean.distance(&input[index], &query), found);
+        }
+    }
+}
+
+#[test]
+fn batch_queries_match_single_queries() {
+    let mut rng = Lcg::new(7);
+    let input: [[i64; 2]; 300] = rng.points();
+    let tree = KDTree::<i64, [i64; 2], 2, 300>::from(input);
+    let queries: Vec<[i64; 2]> = (0..100).map(|_| rng.point()).collect();
+    let found = tree.nearest_batch(&queries);
+    let nearest = tree.k_nearest_batch::<3>(&queries);
+    for (i, query) in queries.iter().enumerate() {
+        let expected = tree.nearest_indexed(query);
+        assert_eq!(found[i], expected.map(|(index, _)| index));
+        assert_eq!(found[i].map(|index| &input[index]), tree.nearest(query));
+        assert_eq!(nearest[i][..], tree.k_nearest_indexed::<3>(query)[..]);
+    }
+}
+
+#[test]
+#[cfg(not(feature = "rayon"))]
+fn batch_queries_need_no_thread_safety_without_rayon() {
+    use std::rc::Rc;
+    /// Point that can neither be sent nor shared between threads.
+    #[derive(Clone)]
+    struct Shared(Rc<[i64; 2]>);
+    impl Point<i64, 2> for Shared {
+        fn get(&self, index: usize) -> i64 {
+            self.0[index]
+        }
+    }
+    let mut rng = Lcg::new(22);
+    let input: [[i64; 2]; 100] = rng.points();
+    let tree = KDTree::<i64, _, 2, 100>::from(input.map(|p| Shared(Rc::new(p))));
+    let queries: Vec<_> = (0..20).map(|_| Shared(Rc::new(rng.point()))).collect();
+    let found = tree.nearest_batch(&queries);
+    for (query, found) in queries.iter().zip(found) {
+        assert_eq!(found, tree.nearest_indexed(query).map(|(index, _)| index));
+    }
+}
+
+#[test]
+fn duplicates_keep_every_point() {
+    let mut rng = Lcg::new(8);
+    let points: [[i64; 3]; 1000] = std::array::from_fn(|_| [rng.next() % 3, rng.next() % 2, 0]);
+    let splits = [
+        Split::Cycle,
+        Split::WidestSpread,
+        Split::SlidingMidpoint,
+        Split::SurfaceArea,
+    ];
+    for split in splits {
+        let tree = KDTreeBuilder::new()
+            .split(split)
+            .build::<i64, _, 3, 1000>(points);
+        if matches!(split, Split::Cycle | Split::WidestSpread) {
+            assert_eq!(tree.depth(), 10);
+        }
+        assert!(tree.depth() <= 16);
+        for query in [[0, 0, 0], [2, 1, 0], [-2, -1, 0], [1, 0, 5]] {
+            let same = points.iter().filter(|&p| p[..2] == query[..2]).count();
+            let flat = [query[0], query[1], 0];
+            assert_eq!(tree.within_radius(&flat, 0).count(), same);
+            assert_eq!(tree.range(&flat, &flat).count(), same);
+            let brute = distances(&points, &query, &SquaredEuclidean);
+            let found = tree.k_nearest::<400>(&query);
+            assert!(
+                found
+                    .iter()
+                    .map(|&(_, d)| d)
+                    .eq(brute[..400].iter().copied())
+            );
+        }
+    }
+}
+
+#[test]
+fn splits_adapt_to_clusters() {
+    let mut rng = Lcg::new(9);
+    // A flat cluster far away from a wide cloud.
+    let points: [[i64; 3]; 300] = std::array::from_fn(|_| match rng.next() % 3 {
+        0 => [rng.next() / 50 + 5000, rng.next() * 100, rng.next() / 100],
+        _ => [rng.next(), rng.next(), rng.next() / 10],
+    });
+    for builder in builders() {
+        let tree = builder.build::<i64, _, 3, 300>(points);
+        check_queries(&tree, &SquaredEuclidean, &mut rng);
+        assert!(tree.pre_order().all(|(_, info)| info.axis < 3));
+    }
+}
+
+/// Coordinate measuring distances in itself and keeping every default
+/// method of [`Coordinate`].
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+struct Tick(i64);
+
+impl std::ops::Add for Tick {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self(self.0 + other.0)
+    }
+}
+
+impl std::ops::Sub for Tick {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self(self.0 - other.0)
+    }
+}
+
+impl std::ops::Mul for Tick {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self(self.0 * other.0)
+    }
+}
+
+impl Coordinate for Tick {
+    type Distance = Self;
+
+    const ZERO: Self = Self(0);
+
+    fn total_cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+
+    fn to_distance(self) -> Self {
+        self
+    }
+}
+
+#[test]
+fn surface_area_falls_back_to_medians_without_conversion() {
+    let mut rng = Lcg::new(21);
+    let points: [[Tick; 2]; 100] = std::array::from_fn(|_| rng.point().map(Tick));
+    let builder = KDTreeBuilder::new().split(Split::SurfaceArea);
+    let tree = builder.build::<Tick, _, 2, 100>(points);
+    let cycle = KDTree::<Tick, _, 2, 100>::from(points);
+    let axes = |tree: &KDTree<Tick, [Tick; 2], 2, 100>| {
+        tree.pre_order()
+            .map(|(_, info)| info.axis)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(axes(&tree), axes(&cycle));
+    for _ in 0..50 {
+        let query = rng.point().map(Tick);
+        let brute = distances(&points, &query, &SquaredEuclidean);
+        let found = tree.k_nearest::<5>(&query);
+        assert!(
+            found
+                .iter()
+                .map(|&(_, d)| d)
+                .eq(brute.iter().copied().take(5))
+        );
+    }
+}
+
+#[test]
+fn lopsided_splits_fall_back_to_medians() {
+    // Midpoints peel off one point at a time here, until depth runs short.
+    let points: [[f64; 1]; 120] = std::array::from_fn(|i| [2_f64.powi(i as i32)]);
+    let builder = KDTreeBuilder::new().split(Split::SlidingMidpoint);
+    let tree = builder.build::<f64, _, 1, 120>(points);
+    assert!(tree.depth() <= 64);
+    assert_eq!(
+        tree.nearest(&[1.2 * 2_f64.powi(101)]),
+        Some(&[2_f64.powi(101)])
+    );
+}
+
+#[test]
+fn splits_handle_spreads_wider_than_the_coordinate_type() {
+    let line = [[-100_i8], [0], [100]];
+    let tree = KDTreeBuilder::new()
+        .split(Split::SlidingMidpoint)
+        .build::<i8, _, 1, 3>(line);
+    assert_eq!(tree.nearest(&[90]), Some(&[100]));
+
+    // Only the second axis spreads over more than `i8::MAX`.
+    let mut rng = Lcg::new(20);
+    let points: [[i8; 2]; 100] =
+        std::array::from_fn(|_| [(rng.next() / 10) as i8, (rng.next() / 4) as i8]);
+    for split in [
+        Split::WidestSpread,
+        Split::SlidingMidpoint,
+        Split::SurfaceArea,
+    ] {
+        let tree = KDTreeBuilder::new()
+            .split(split)
+            .build::<i8, _, 2, 100>(points);
+        assert_eq!(tree.pre_order().next().map(|(_, info)| info.axis), Some(1));
+        for query in [[0, 0], [-128, 127], [50, -120]] {
+            let brute = distances(&points, &query, &SquaredEuclidean);
+            let found = tree.k_nearest::<10>(&query);
+            assert!(
+                found
+                    .iter()
+                    .map(|&(_, d)| d)
+                    .eq(brute.iter().copied().take(10))
+            );
+        }
+    }
+}
+
+#[test]
+fn walks_visit_every_point() {
+    let mut rng = Lcg::new(10);
+    let points: [[i64; 1]; 300] = rng.points();
+    let mut sorted = points;
+    sorted.sort();
+    let tree = KDTree::<i64, _, 1, 300>::from(points);
+    assert!(tree.in_order().map(|(p, _)| *p).eq(sorted));
+    let tree = KDTreeBuilder::new()
+        .bucket_size(8)
+        .build::<i64, _, 1, 300>(points);
+    let found: Vec<_> = tree.in_order().collect();
+    for pair in found.windows(2) {
+        let ((a, x), (b, y)) = (&pair[0], &pair[1]);
+        assert!(a <= b || (x.leaf && x.bounds == y.bounds));
+    }
+
+    let tree = KDTreeBuilder::new()
+        .bucket_size(3)
+        .build::<i64, [i64; 2], 2, 300>(rng.points());
+    for walk in [tree.pre_order(), tree.in_order(), tree.level_order()] {
+        let mut indices: Vec<_> = walk.map(|(_, info)| info.index).collect();
+        indices.sort();
+        assert!(indices.into_iter().eq(0..300));
+    }
+    let depths: Vec<_> = tree.level_order().map(|(_, info)| info.depth).collect();
+    assert!(depths.is_sorted());
+    for (point, info) in tree.level_order() {
+        assert!(info.bounds.contains(&info.index));
+        assert_eq!(info.axis, info.depth % 2);
+        assert_eq!(point, &tree.points()[info.index]);
+    }
+}
+
+#[test]
+fn implicit_tree_matches_explicit() {
+    let mut rng = Lcg::new(11);
+    let points: [[i64; 3]; 300] = rng.points();
+    let implicit = ImplicitKDTree::<i64, _, 3, 300>::from(points);
+    let explicit = KDTree::<i64, _, 3, 300>::from(points);
+    let bucketed = KDTreeBuilder::new()
+        .bucket_size(4)
+        .build_implicit::<i64, _, 3, 300>(points);
+    assert_eq!(implicit.points(), explicit.points());
+    for _ in 0..100 {
+        let query = rng.point();
+        assert_eq!(implicit.nearest(&query), explicit.nearest(&query));
+        let nearest = explicit.k_nearest::<5>(&query);
+        assert_eq!(implicit.k_nearest::<5>(&query)[..], nearest[..]);
+        let found = bucketed.k_nearest::<5>(&query);
+        assert!(
+            found
+                .iter()
+                .map(|(_, d)| d)
+                .eq(nearest.iter().map(|(_, d)| d))
+        );
+        let radius = rng.next().abs() / 4;
+        let found = implicit.within_radius(&query, radius);
+        assert!(found.eq(explicit.within_radius(&query, radius)));
+        let max = query.map(|x| x + radius);
+        assert!(
+            implicit
+                .range(&query, &max)
+                .eq(explicit.range(&query, &max))
+        );
+        let found = bucketed.range(&query, &max);
+        assert_eq!(found.count(), explicit.range(&query, &max).count());
+    }
+}
+
+#[test]
+fn dynamic_tree_matches_brute_force() {
+    let mut rng = Lcg::new(12);
+    let mut tree = DynamicKDTree::<i64, [i64; 2], 2>::new();
+    let mut points = Vec::new();
+    for round in 0..2000 {
+        if round % 3 == 2 {
+            let point = points.swap_remove(rng.next().unsigned_abs() as usize % points.len());
+            assert!(tree.remove(&point));
+        } else {
+            let point = rng.point();
+            tree.insert(point);
+            points.push(point);
+        }
+        let query = rng.point();
+        let brute = distances(&points, &query, &SquaredEuclidean);
+        assert_eq!(tree.len(), points.len());
+        let found = tree
+            .nearest(&query)
+            .map(|p| SquaredEuclidean.distance(p, &query));
+        assert_eq!(found, brute.first().copied());
+    }
+}
+
+#[test]
+fn all_nearest_matches_brute_force() {
+    let mut rng = Lcg::new(13);
+    let points: [[i64; 3]; 300] = rng.points();
+    let others: [[i64; 3]; 200] = std::array::from_fn(|_| {
+        let [x, y, z] = rng.point();
+        [x, y, z / 2]
+    });
+    for builder in builders() {
+        let tree = builder.build::<i64, _, 3, 300>(points);
+        let other = builder.bucket_size(3).build::<i64, _, 3, 200>(others);
+        let found = tree.all_nearest(&other);
+        for (point, found) in points.iter().zip(found) {
+            let (index, d) = found.unwrap();
+            assert_eq!(SquaredEuclidean.distance(point, &others[index]), d);
+            assert_eq!(distances(&others, point, &SquaredEuclidean)[0], d);
+        }
+        let found = other.all_nearest_by(&tree, &Manhattan);
+        for (point, found) in others.iter().zip(found) {
+            let (index, d) = found.unwrap();
+            assert_eq!(Manhattan.distance(point, &points[index]), d);
+            assert_eq!(distances(&points, point, &Manhattan)[0], d);
+        }
+    }
+}
+
+#[test]
+fn join_within_matches_brute_force() {
+    let mut rng = Lcg::new(14);
+    let points: [[i64; 2]; 300] = rng.points();
+    let others: [[i64; 2]; 250] = std::array::from_fn(|_| [rng.next() / 2, rng.next()]);
+    for builder in builders() {
+        let tree = builder.build::<i64, _, 2, 300>(points);
+        let other = builder.build::<i64, _, 2, 250>(others);
+        for radius in [0, 10, 45, 200] {
+            let mut found: Vec<_> = tree.join_within(&other, radius).collect();
+            found.sort();
+            assert_eq!(
+                found,
+                cross_pairs(
+                    &points,
+                    &others,
+                    u128::from(radius.unsigned_abs()).pow(2),
+                    &SquaredEuclidean
+                )
+            );
+            let mut found: Vec<_> = tree
+                .join_within_by(&other, radius.unsigned_abs().into(), &Chebyshev)
+                .collect();
+            found.sort();
+            assert_eq!(
+                found,
+                cross_pairs(&points, &others, radius.unsigned_abs().into(), &Chebyshev)
+            );
+        }
+    }
+}
+
+/// Every pair of positions in `points` and `others` at most `radius` apart,
+/// sorted.
+fn cross_pairs<D>(
+    points: &[[i64; 2]],
+    others: &[[i64; 2]],
+    radius: u128,
+    metric: &D,
+) -> Vec<(usize, usize)>
+where
+    D: Metric<i64, 2>,
+{
+    let pairs = (0..points.len()).flat_map(|i| (0..others.len()).map(move |j| (i, j)));
+    pairs
+        .filter(|&(i, j)| metric.distance(&points[i], &others[j]) <= radius)
+        .collect()
+}
+
+#[test]
+fn pairs_within_matches_brute_force() {
+    let mut rng = Lcg::new(15);
+    let mut points: [[i64; 2]; 300] = std::array::from_fn(|_| [rng.next(), rng.next() / 3]);
+    points[7] = points[250];
+    for builder in builders() {
+        let tree = builder.build::<i64, _, 2, 300>(points);
+        for radius in [0, 8, 30, 150] {
+            let mut found: Vec<_> = tree.pairs_within(radius).collect();
+            found.sort();
+            let squared = u128::from(radius.unsigned_abs()).pow(2);
+            assert_eq!(found, closer_pairs(&points, squared, &SquaredEuclidean));
+            let radius = radius.unsigned_abs().into();
+            let mut found: Vec<_> = tree.pairs_within_by(radius, &Manhattan).collect();
+            found.sort();
+            assert_eq!(found, closer_pairs(&points, radius, &Manhattan));
+        }
+    }
+}
+
+/// Every pair of positions in `points`, smaller one first, of points closer
+/// than `radius`, sorted.
+fn closer_pairs<D>(points: &[[i64; 2]], radius: u128, metric: &D) -> Vec<(usize, usize)>
+where
+    D: Metric<i64, 2>,
+{
+    let pairs = (0..points.len()).flat_map(|i| (i + 1..points.len()).map(move |j| (i, j)));
+    pairs
+        .filter(|&(i, j)| metric.distance(&points[i], &points[j]) < radius)
+        .collect()
+}
+
+#[test]
+fn negative_radii_panic() {
+    let points = [[0, 0], [1, 1], [2, 2]];
+    let tree = KDTree::<i32, _, 2, 3>::from(points);
+    let implicit = ImplicitKDTree::<i32, _, 2, 3>::from(points);
+    let floats = KDTree::<f64, [f64; 2], 2, 1>::from([[0.0, 0.0]]);
+    let panics = |query: &dyn Fn() -> usize| {
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(query)).is_err()
+    };
+    assert!(panics(&|| tree.within_radius(&[0, 0], -2).count()));
+    assert!(panics(&|| implicit.within_radius(&[0, 0], -2).count()));
+    assert!(panics(&|| floats.within_radius(&[0.0, 0.0], -2.0).count()));
+    assert!(panics(&|| tree.join_within(&tree, -2).count()));
+    assert!(panics(&|| tree.pairs_within(-2).count()));
+    assert_eq!(tree.within_radius(&[0, 0], 0).count(), 1);
+    assert_eq!(floats.within_radius(&[0.0, 0.0], f64::NAN).count(), 0);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "queries must not have NaN coordinates")]
+fn nan_queries_panic_in_debug_builds() {
+    let tree = KDTree::<f32, [f32; 2], 2, 2>::from([[0.0, 0.0], [1.0, 1.0]]);
+    tree.nearest(&[f32::NAN, 0.0]);
+}
+
+#[test]
+#[cfg(feature = "rayon")]
+fn parallel_build_matches_sequential() {
+    const N: usize = 50_000;
+    let mut rng = Lcg::new(16);
+    let points: Vec<[f32; 3]> = (0..N).map(|_| [(); 3].map(|_| rng.unit() as f32)).collect();
+    let boxed = || -> Box<[[f32; 3]; N]> { points.clone().into_boxed_slice().try_into().unwrap() };
+    for builder in builders() {
+        let tree = builder.par_build_boxed::<f32, _, 3, N>(boxed());
+        let sequential = builder.build_boxed::<f32, _, 3, N>(boxed());
+        assert_eq!(tree.points(), sequential.points());
+        assert_eq!(tree.indices(), sequential.indices());
+    }
+
+    const M: usize = 6_000;
+    let points: [[f32; 3]; M] = points[..M].try_into().unwrap();
+    let builder = KDTreeBuilder::new().bucket_size(8);
+    let tree = builder.par_build_implicit::<f32, _, 3, M>(points);
+    let explicit = builder.build_boxed::<f32, _, 3, M>(Box::new(points));
+    assert_eq!(tree.points(), explicit.points());
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn loaded_trees_answer_like_dumped_ones() {
+    let mut rng = Lcg::new(17);
+    let points: [[i64; 2]; 300] = rng.points();
+    for builder in [
+        KDTreeBuilder::new(),
+        KDTreeBuilder::new().tight_bounds(true),
+    ] {
+        let tree = builder.build::<i64, _, 2, 300>(points);
+        let mut bytes = Vec::new();
+        tree.dump(&mut bytes).unwrap();
+        let loaded = KDTree::<i64, [i64; 2], 2, 300>::load(&bytes[..]).unwrap();
+        let json = serde_json::to_string(&tree).unwrap();
+        let parsed: KDTree<i64, [i64; 2], 2, 300> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.points(), tree.points());
+        assert_eq!(parsed.points(), tree.points());
+        assert_eq!(loaded.bounds(), tree.bounds());
+        for _ in 0..100 {
+            let query = rng.point();
+            assert_eq!(loaded.nearest(&query), tree.nearest(&query));
+            assert_eq!(
+                parsed.k_nearest::<4>(&query)[..],
+                tree.k_nearest::<4>(&query)[..]
+            );
+            let radius = rng.next().abs() / 2;
+            let found = loaded.within_radius(&query, radius);
+            assert_eq!(found.count(), tree.within_radius(&query, radius).count());
+        }
+        assert!(KDTree::<i64, [i64; 2], 2, 300>::load(&bytes[..bytes.len() / 2]).is_err());
+        assert!(KDTree::<i64, [i64; 2], 2, 299>::load(&bytes[..]).is_err());
+    }
+}
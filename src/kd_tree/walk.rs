@@ -0,0 +1,188 @@
+use crate::stack_vec::StackVec;
+
+use super::{Coordinate, KDTree, MAX_DEPTH, Node, Point, View};
+
+/// Where a point sits in a built tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeInfo {
+    /// Position of the point in `points`.
+    pub index: usize,
+    /// Axis the node splits on, leaves keep the one they would split on.
+    pub axis: usize,
+    /// Number of nodes above the one holding the point.
+    pub depth: usize,
+    /// Positions in `points` covered by the subtree of the node.
+    pub bounds: std::ops::Range<usize>,
+    /// Whether the point lies in a leaf bucket instead of splitting space.
+    pub leaf: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Order {
+    Pre,
+    In,
+    Level,
+}
+
+/// Lazily yields every point of a tree together with its [`NodeInfo`],
+/// points of a leaf bucket come one after another.
+pub struct Walk<'a, T, P, const K: usize>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    view: View<'a, T, P, K>,
+    order: Order,
+    /// Nodes left to visit with their depth and whether their left subtree
+    /// is already on the stack.
+    stack: StackVec<(Node, usize, bool), { 2 * MAX_DEPTH }>,
+    leaf: Option<(Node, usize)>,
+    bucket: std::ops::Range<usize>,
+    /// Depth yielded by a level order walk and whether any node lies below.
+    level: usize,
+    deeper: bool,
+}
+
+impl<'a, T, P, const K: usize> Walk<'a, T, P, K>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    fn new(view: View<'a, T, P, K>, order: Order) -> Self {
+        let mut walk = Self {
+            view,
+            order,
+            stack: StackVec::new(),
+            leaf: None,
+            bucket: 0..0,
+            level: 0,
+            deeper: false,
+        };
+        walk.push_root();
+        walk
+    }
+
+    fn push_root(&mut self) {
+        if let Some(root) = self.view.root() {
+            self.stack.push((root, 0, false));
+        }
+    }
+
+    fn push_children(&mut self, node: &Node, depth: usize) {
+        let (left, right) = self.view.children(node);
+        if let Some(right) = right {
+            self.stack.push((right, depth + 1, false));
+        }
+        if let Some(left) = left {
+            self.stack.push((left, depth + 1, false));
+        }
+    }
+
+    fn item(&self, node: &Node, depth: usize, index: usize) -> (&'a P, NodeInfo) {
+        let info = NodeInfo {
+            index,
+            axis: node.axis,
+            depth,
+            bounds: node.bound_left..node.bound_right,
+            leaf: node.is_leaf(),
+        };
+        (&self.view.points[index], info)
+    }
+}
+
+impl<'a, T, P, const K: usize> Iterator for Walk<'a, T, P, K>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    type Item = (&'a P, NodeInfo);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((node, depth)) = self.leaf
+                && let Some(index) = self.bucket.next()
+            {
+                return Some(self.item(&node, depth, index));
+            }
+            let Some((node, depth, expanded)) = self.stack.pop() else {
+                if self.order == Order::Level && self.deeper {
+                    self.level += 1;
+                    self.deeper = false;
+                    self.push_root();
+                    continue;
+                }
+                return None;
+            };
+            if self.order == Order::Level && depth < self.level {
+                self.push_children(&node, depth);
+                continue;
+            }
+            if node.is_leaf() {
+                self.leaf = Some((node, depth));
+                self.bucket = node.bucket();
+                continue;
+            }
+            match self.order {
+                Order::Pre => self.push_children(&node, depth),
+                Order::In if !expanded => {
+                    let (left, right) = self.view.children(&node);
+                    if let Some(right) = right {
+                        self.stack.push((right, depth + 1, false));
+                    }
+                    self.stack.push((node, depth, true));
+                    if let Some(left) = left {
+                        self.stack.push((left, depth + 1, false));
+                    }
+                    continue;
+                }
+                Order::In => {}
+                Order::Level => self.deeper = true,
+            }
+            return Some(self.item(&node, depth, node.index));
+        }
+    }
+}
+
+impl<T, P, const K: usize, const N: usize> KDTree<T, P, K, N>
+where
+    P: Point<T, K>,
+    T: Coordinate,
+{
+    /// Lazily yields every point before the points of its subtrees, the
+    /// left subtree first.
+    /// ```
+    /// use kuina::kd_tree::KDTree;
+    /// let tree = KDTree::<u32, [u32; 1], 1, 7>::from([6, 5, 4, 3, 2, 1, 0].map(|x| [x]));
+    /// let found: Vec<_> = tree.pre_order().map(|(p, info)| (p[0], info.depth)).collect();
+    /// assert_eq!(found, [(3, 0), (1, 1), (0, 2), (2, 2), (5, 1), (4, 2), (6, 2)]);
+    /// let (_, root) = tree.pre_order().next().unwrap();
+    /// assert_eq!((root.axis, root.bounds, root.leaf), (0, 0..7, false));
+    /// ```
+    pub fn pre_order(&self) -> Walk<'_, T, P, K> {
+        Walk::new(self.view(), Order::Pre)
+    }
+
+    /// Lazily yields every point between the points of its left and right
+    /// subtrees, so points of a one dimensional tree come sorted.
+    /// ```
+    /// use kuina::kd_tree::KDTree;
+    /// let tree = KDTree::<u32, [u32; 1], 1, 5>::from([3, 0, 4, 1, 2].map(|x| [x]));
+    /// assert!(tree.in_order().map(|(p, _)| p[0]).eq(0..5));
+    /// ```
+    pub fn in_order(&self) -> Walk<'_, T, P, K> {
+        Walk::new(self.view(), Order::In)
+    }
+
+    /// Lazily yields the points level by level from the root, every level
+    /// from left to right. Walking down to every level again keeps it free
+    /// of allocations at the cost of a logarithmic factor.
+    /// ```
+    /// use kuina::kd_tree::KDTree;
+    /// let tree = KDTree::<u32, [u32; 1], 1, 7>::from([6, 5, 4, 3, 2, 1, 0].map(|x| [x]));
+    /// let found: Vec<_> = tree.level_order().map(|(p, _)| p[0]).collect();
+    /// assert_eq!(found, [3, 1, 5, 0, 2, 4, 6]);
+    /// ```
+    pub fn level_order(&self) -> Walk<'_, T, P, K> {
+        Walk::new(self.view(), Order::Level)
+    }
+}
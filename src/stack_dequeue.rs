@@ -11,6 +11,12 @@ pub struct StackDequeue<T, const N: usize> {
     size: usize,
 }
 
+impl<T, const N: usize> Default for StackDequeue<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T, const N: usize> StackDequeue<T, N> {
     /// ```
     /// use kuina::stack_dequeue::StackDequeue;
@@ -35,6 +41,17 @@ impl<T, const N: usize> StackDequeue<T, N> {
         self.size
     }
 
+    /// ```
+    /// use kuina::stack_dequeue::StackDequeue;
+    /// let mut deq = StackDequeue::<_, 2>::new();
+    /// assert!(deq.is_empty());
+    /// deq.push_back(1);
+    /// assert!(!deq.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
     fn get_index(&self, index: usize) -> usize {
         let index = self.start + index;
         if index < N { index } else { index - N }